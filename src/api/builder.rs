@@ -0,0 +1,193 @@
+use super::cassette::Cassette;
+use super::middleware::Middleware;
+use super::rate_limit::RateLimiter;
+use super::retry::RetryConfig;
+use super::WeLoveClient;
+use crate::error::{WeLoveError, WeLoveResult};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct WeLoveClientBuilder {
+    base_url: Option<String>,
+    fv: Option<String>,
+    union_id: Option<String>,
+    params: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    user_agent: Option<String>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    retry: Option<RetryConfig>,
+    rate_limiter: Option<RateLimiter>,
+    cassette: Option<Cassette>,
+    middlewares: Vec<Box<dyn Middleware>>,
+    dry_run: bool,
+}
+
+impl WeLoveClientBuilder {
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// App version sent as the `fv` param.
+    pub fn fv(mut self, fv: impl Into<String>) -> Self {
+        self.fv = Some(fv.into());
+        self
+    }
+
+    pub fn union_id(mut self, union_id: impl Into<String>) -> Self {
+        self.union_id = Some(union_id.into());
+        self
+    }
+
+    /// Adds a param sent (and signed) with every request.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of a whole request, from connecting until the response body is read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// HTTP(S) proxy for every request, e.g. `http://127.0.0.1:8888`.
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Adds a middleware layer. Layers run in the order they are added.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    pub fn cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Logs requests that change the farm instead of sending them, see [`WeLoveClient::dry_run`].
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn build(self) -> WeLoveResult<WeLoveClient> {
+        let base_url = self
+            .base_url
+            .ok_or(WeLoveError::Config("base_url is not set".to_string()))?;
+        let default_headers = (&self.headers)
+            .try_into()
+            .map_err(|e| WeLoveError::Config(format!("invalid header: {e}")))?;
+        let mut http_client = reqwest::ClientBuilder::new().default_headers(default_headers);
+        if let Some(user_agent) = self.user_agent {
+            http_client = http_client.user_agent(user_agent);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http_client = http_client.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            http_client = http_client.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            http_client = http_client.proxy(reqwest::Proxy::all(proxy)?);
+        }
+
+        let mut default_params = self.params;
+        if let Some(fv) = self.fv {
+            default_params.insert("fv".to_string(), fv);
+        }
+        if let Some(union_id) = self.union_id {
+            default_params.insert("union_id".to_string(), union_id);
+        }
+        Ok(WeLoveClient {
+            http_client: http_client.build()?,
+            base_url,
+            default_params,
+            cassette: self.cassette,
+            retry: self.retry.unwrap_or_default(),
+            rate_limiter: self.rate_limiter.unwrap_or_default(),
+            middlewares: self.middlewares,
+            dry_run: self.dry_run,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let cli = WeLoveClient::builder()
+            .base_url("http://127.0.0.1:1")
+            .fv("1.0")
+            .union_id("union")
+            .user_agent("welove520")
+            .timeout(Duration::from_secs(5))
+            .proxy("http://127.0.0.1:8888")
+            .build()
+            .unwrap();
+        assert_eq!(cli.default_params["fv"], "1.0");
+        assert_eq!(cli.default_params["union_id"], "union");
+
+        #[allow(deprecated)]
+        let cli = WeLoveClient::new(
+            "http://127.0.0.1:1".to_string(),
+            HashMap::from([("fv".to_string(), "1.0".to_string())]),
+            Default::default(),
+        )
+        .with_retry(RetryConfig::default());
+        assert_eq!(cli.default_params["fv"], "1.0");
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert!(matches!(
+            WeLoveClient::builder().build(),
+            Err(WeLoveError::Config(_))
+        ));
+        assert!(matches!(
+            WeLoveClient::builder()
+                .base_url("http://127.0.0.1:1")
+                .header("bad header", "value")
+                .build(),
+            Err(WeLoveError::Config(_))
+        ));
+        assert!(WeLoveClient::builder()
+            .base_url("http://127.0.0.1:1")
+            .proxy("not a proxy url")
+            .build()
+            .is_err());
+    }
+}
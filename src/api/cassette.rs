@@ -0,0 +1,160 @@
+use crate::error::{WeLoveError, WeLoveResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Params that change on every request and are left out of the recorded exchange.
+/// `check` is the random nonce sent by `stall_onshelf`.
+const VOLATILE_PARAMS: [&str; 4] = ["sig", "ts", "union_id", "check"];
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Interaction {
+    pub path: String,
+    pub params: BTreeMap<String, String>,
+    pub response: String,
+}
+
+impl Interaction {
+    fn new(path: &str, params: &HashMap<&str, &str>, response: String) -> Self {
+        Self {
+            path: path.to_string(),
+            params: stable_params(params),
+            response,
+        }
+    }
+}
+
+fn stable_params(params: &HashMap<&str, &str>) -> BTreeMap<String, String> {
+    params
+        .iter()
+        .filter(|(k, _)| !VOLATILE_PARAMS.contains(k))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// A file of recorded request/response pairs, one JSON [`Interaction`] per line.
+///
+/// In [`CassetteMode::Record`] every exchange is appended to the file. In [`CassetteMode::Replay`]
+/// requests are answered from the file in recorded order without touching the network.
+#[derive(Debug)]
+pub struct Cassette {
+    pub path: PathBuf,
+    pub mode: CassetteMode,
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Creates (or truncates) the cassette file at `path`.
+    pub fn record(path: impl AsRef<Path>) -> WeLoveResult<Self> {
+        std::fs::File::create(path.as_ref())
+            .map_err(|e| WeLoveError::Other(format!("failed to create cassette: {e}")))?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Record,
+            interactions: Default::default(),
+        })
+    }
+
+    pub fn replay(path: impl AsRef<Path>) -> WeLoveResult<Self> {
+        let content = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| WeLoveError::Other(format!("failed to read cassette: {e}")))?;
+        let interactions = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map(|i| (i, false)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Replay,
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    pub fn save(
+        &self,
+        path: &str,
+        params: &HashMap<&str, &str>,
+        response: &str,
+    ) -> WeLoveResult<()> {
+        let interaction = Interaction::new(path, params, response.to_string());
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| WeLoveError::Other(format!("failed to open cassette: {e}")))?;
+        writeln!(file, "{}", serde_json::to_string(&interaction)?)
+            .map_err(|e| WeLoveError::Other(format!("failed to write cassette: {e}")))?;
+        self.interactions.lock().unwrap().push((interaction, true));
+        Ok(())
+    }
+
+    /// Returns the raw response of the first unused interaction matching `path` and `params`.
+    pub fn find(&self, path: &str, params: &HashMap<&str, &str>) -> WeLoveResult<String> {
+        let params = stable_params(params);
+        let mut interactions = self.interactions.lock().unwrap();
+        let (interaction, used) = interactions
+            .iter_mut()
+            .find(|(i, used)| !*used && i.path == path && i.params == params)
+            .ok_or_else(|| {
+                WeLoveError::Other(format!(
+                    "no recorded interaction for {path} {}",
+                    serde_json::to_string(&params).unwrap_or_default()
+                ))
+            })?;
+        *used = true;
+        Ok(interaction.response.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::market::MarketApi;
+    use crate::api::order::OrderApi;
+    use crate::api::stall::StallApi;
+    use crate::api::tests::get_mock_client;
+    use crate::api::WeLoveClient;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!(
+            "welove520-cassette-{}.jsonl",
+            rand::random::<u64>()
+        ));
+        let (mut cli, _) = get_mock_client();
+        cli.cassette = Some(Cassette::record(&path).unwrap());
+        let recorded_market = cli.market_query().await.unwrap();
+        let recorded_resp = cli
+            .stall_onshelf(2, 201001, 10, 36, false, 0)
+            .await
+            .unwrap();
+        let recorded_orders = cli.order_query().await.unwrap();
+
+        let cli = WeLoveClient::builder()
+            .base_url("http://127.0.0.1:1")
+            .fv("mock")
+            .union_id("another")
+            .cassette(Cassette::replay(&path).unwrap())
+            .build()
+            .unwrap();
+        let market = cli.market_query().await.unwrap();
+        assert_eq!(market.next_refresh_time, recorded_market.next_refresh_time);
+        let resp = cli
+            .stall_onshelf(2, 201001, 10, 36, false, 0)
+            .await
+            .unwrap();
+        assert_eq!(resp.messages, recorded_resp.messages);
+        let orders = cli.order_query().await.unwrap();
+        assert_eq!(orders.orders.len(), recorded_orders.orders.len());
+        assert!(cli.order_query().await.is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,7 +1,6 @@
-use std::collections::HashMap;
-
 use super::{Response, WeLoveClient};
 use crate::error::WeLoveResult;
+use crate::utils::form::comma_separated;
 use serde::{Deserialize, Serialize};
 
 pub trait CropsApi {
@@ -13,7 +12,7 @@ pub trait CropsApi {
     async fn crops_harvest(&self, item_id: i64, farmland_ids: Vec<i64>) -> WeLoveResult<Response>;
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Farmland {
     pub id: i64,
@@ -22,37 +21,29 @@ pub struct Farmland {
     pub y: i64,
 }
 
+crate::endpoint! {
+    pub struct CropsPlantRequest {
+        pub item_id: i64,
+        pub farmlands: Vec<Farmland>,
+    } => "/v1/game/farm/crops/plant" -> Response;
+
+    pub struct CropsHarvestRequest {
+        pub item_id: i64,
+        #[serde(serialize_with = "comma_separated")]
+        pub farmland_ids: Vec<i64>,
+    } => "/v1/game/farm/crops/harvest" -> Response;
+}
+
 impl CropsApi for WeLoveClient {
     async fn crops_plant(&self, item_id: i64, farmlands: Vec<Farmland>) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/crops/plant",
-            HashMap::from([
-                ("item_id", item_id.to_string().as_str()),
-                (
-                    "farmlands",
-                    serde_json::to_string(&farmlands).unwrap().as_str(),
-                ),
-            ]),
-        )
-        .await
+        self.call(&CropsPlantRequest { item_id, farmlands }).await
     }
 
     async fn crops_harvest(&self, item_id: i64, farmland_ids: Vec<i64>) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/crops/harvest",
-            HashMap::from([
-                ("item_id", item_id.to_string().as_str()),
-                (
-                    "farmland_ids",
-                    farmland_ids
-                        .into_iter()
-                        .map(|id| id.to_string())
-                        .collect::<Vec<_>>()
-                        .join(",")
-                        .as_str(),
-                ),
-            ]),
-        )
+        self.call(&CropsHarvestRequest {
+            item_id,
+            farmland_ids,
+        })
         .await
     }
 }
@@ -0,0 +1,136 @@
+use super::{FromResponse, WeLoveClient};
+use crate::error::WeLoveResult;
+use serde::Serialize;
+
+/// A request type bound to its path and to what is extracted from the response.
+pub trait Endpoint: Serialize {
+    const PATH: &'static str;
+    type Response: FromResponse;
+}
+
+impl WeLoveClient {
+    pub async fn call<E: Endpoint>(&self, req: &E) -> WeLoveResult<E::Response> {
+        self.post_typed(E::PATH, req).await
+    }
+}
+
+/// Declares request structs and their [`Endpoint`] impls.
+///
+/// ```
+/// use welove520::api::market::MarketInfo;
+/// use welove520::api::Response;
+///
+/// welove520::endpoint! {
+///     pub struct MarketQuery {} => "/v1/game/farm/market/query" -> MarketInfo;
+///
+///     pub struct MarketSale {
+///         pub item_id: i64,
+///         pub count: i64,
+///     } => "/v1/game/farm/market/sale" -> Response;
+/// }
+/// ```
+#[macro_export]
+macro_rules! endpoint {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $field_ty:ty
+            ),* $(,)?
+        } => $path:literal -> $resp:ty;
+    )*) => {$(
+        $(#[$meta])*
+        #[derive(Default, Debug, Clone, $crate::serde::Serialize)]
+        #[serde(crate = "welove520::serde")]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $field_ty,
+            )*
+        }
+
+        impl $crate::api::endpoint::Endpoint for $name {
+            const PATH: &'static str = $path;
+            type Response = $resp;
+        }
+    )*};
+}
+
+/// Implements [`FromResponse`] for a type taken from the first message of a `msg_type`.
+///
+/// `from_message!(T => Variant, msg_type)` takes the payload of a [`Message`](super::message::Message) variant.
+/// `from_message!(T, msg_type)` deserializes the raw message, and `from_message!(T, msg_type, "key")`
+/// deserializes one key of it, so message types without a variant need no changes to `Message`.
+#[macro_export]
+macro_rules! from_message {
+    ($ty:ty => $variant:ident, $msg_type:literal) => {
+        impl $crate::api::FromResponse for $ty {
+            fn from_response(resp: $crate::api::Response) -> $crate::error::WeLoveResult<Self> {
+                resp.into_messages()
+                    .into_iter()
+                    .find_map(|m| match m {
+                        $crate::api::message::Message::$variant(v) => Some(v),
+                        _ => None,
+                    })
+                    .ok_or($crate::error::WeLoveError::None(concat!(
+                        "failed to get message msg_type=",
+                        $msg_type
+                    )))
+            }
+        }
+    };
+    ($ty:ty, $msg_type:literal $(, $key:literal)?) => {
+        impl $crate::api::FromResponse for $ty {
+            fn from_response(resp: $crate::api::Response) -> $crate::error::WeLoveResult<Self> {
+                #[allow(unused_mut)]
+                let mut msg = resp
+                    .messages
+                    .into_iter()
+                    .find(|m| m["msg_type"] == $msg_type)
+                    .ok_or($crate::error::WeLoveError::None(concat!(
+                        "failed to get message msg_type=",
+                        $msg_type
+                    )))?;
+                $(let msg = msg[$key].take();)?
+                $crate::serde_json::from_value(msg).map_err($crate::error::WeLoveError::from)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::panorama::{Field, Warehouse};
+    use crate::api::tests::get_mock_client;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct FieldIds(Vec<FieldId>);
+
+    #[derive(Debug, Deserialize)]
+    struct FieldId {
+        id: i64,
+    }
+
+    crate::from_message!(FieldIds, 2, "fields");
+
+    crate::endpoint! {
+        struct Panorama {} => "/v1/game/farm/panorama" -> (Vec<Field>, Vec<Warehouse>);
+
+        struct PanoramaFieldIds {} => "/v1/game/farm/panorama" -> FieldIds;
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let (cli, _) = get_mock_client();
+        let (fields, warehouses) = cli.call(&Panorama {}).await.unwrap();
+        assert_eq!(fields.len(), 5);
+        assert!(!warehouses.is_empty());
+        let ids = cli.call(&PanoramaFieldIds {}).await.unwrap();
+        assert_eq!(
+            ids.0.iter().map(|f| f.id).collect::<Vec<_>>(),
+            fields.iter().map(|f| f.id).collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,6 +1,5 @@
-use crate::error::{WeLoveError, WeLoveResult};
+use crate::error::WeLoveResult;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 use super::{Response, WeLoveClient};
 pub trait MarketApi {
@@ -29,36 +28,30 @@ pub struct MarketItem {
     pub coin: i64,
 }
 
+crate::endpoint! {
+    pub struct MarketSaleRequest {
+        pub item_id: i64,
+        pub count: i64,
+    } => "/v1/game/farm/market/sale" -> Response;
+
+    pub struct MarketQueryRequest {} => "/v1/game/farm/market/query" -> MarketInfo;
+
+    pub struct MarketBuyRequest {
+        pub id: i64,
+    } => "/v1/game/farm/market/buy" -> Response;
+}
+
 impl MarketApi for WeLoveClient {
     async fn market_sale(&self, item_id: i64, count: i64) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/market/sale",
-            HashMap::from([
-                ("item_id", item_id.to_string().as_str()),
-                ("count", count.to_string().as_str()),
-            ]),
-        )
-        .await
+        self.call(&MarketSaleRequest { item_id, count }).await
     }
 
     async fn market_query(&self) -> WeLoveResult<MarketInfo> {
-        serde_json::from_value(
-            self.post("/v1/game/farm/market/query", Default::default())
-                .await?
-                .messages
-                .into_iter()
-                .find(|m| m["msg_type"] == 920)
-                .ok_or(WeLoveError::None("failed to get message msg_type=920"))?,
-        )
-        .map_err(WeLoveError::from)
+        self.call(&MarketQueryRequest {}).await
     }
 
     async fn market_buy(&self, id: i64) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/market/buy",
-            HashMap::from([("id", id.to_string().as_str())]),
-        )
-        .await
+        self.call(&MarketBuyRequest { id }).await
     }
 }
 
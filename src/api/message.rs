@@ -0,0 +1,123 @@
+use super::market::MarketInfo;
+use super::order::{Order, OrderInfo};
+use super::panorama::{Field, Warehouse};
+use super::stall::StallInfo;
+use super::{FromResponse, Response};
+use crate::error::WeLoveResult;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A typed entry of `Response.messages`, keyed by its `msg_type`.
+#[derive(Debug, Clone)]
+pub enum Message {
+    Fields(Vec<Field>),
+    Warehouses(Vec<Warehouse>),
+    Orders(OrderInfo),
+    Stall(StallInfo),
+    NewOrder(Order),
+    Market(MarketInfo),
+    Unknown(Value),
+}
+
+impl Message {
+    pub const FIELDS: i64 = 2;
+    pub const WAREHOUSES: i64 = 3;
+    pub const ORDERS: i64 = 15;
+    pub const STALL: i64 = 20;
+    pub const NEW_ORDER: i64 = 47;
+    pub const MARKET: i64 = 920;
+
+    /// Parses one message, keeping it as [`Message::Unknown`] if it does not match its schema.
+    pub fn from_value(value: Value) -> Self {
+        let msg_type = value["msg_type"].as_i64().unwrap_or_default();
+        let parsed = match msg_type {
+            Self::FIELDS => Deserialize::deserialize(&value["fields"]).map(Self::Fields),
+            Self::WAREHOUSES => {
+                Deserialize::deserialize(&value["warehouses"]).map(Self::Warehouses)
+            }
+            Self::ORDERS => Deserialize::deserialize(&value).map(Self::Orders),
+            Self::STALL => Deserialize::deserialize(&value).map(Self::Stall),
+            Self::NEW_ORDER => Deserialize::deserialize(&value).map(Self::NewOrder),
+            Self::MARKET => Deserialize::deserialize(&value).map(Self::Market),
+            _ => return Self::Unknown(value),
+        };
+        parsed.unwrap_or_else(|err| {
+            tracing::warn!("failed to parse message msg_type={msg_type}: {err}");
+            Self::Unknown(value)
+        })
+    }
+
+    pub fn msg_type(&self) -> i64 {
+        match self {
+            Self::Fields(_) => Self::FIELDS,
+            Self::Warehouses(_) => Self::WAREHOUSES,
+            Self::Orders(_) => Self::ORDERS,
+            Self::Stall(_) => Self::STALL,
+            Self::NewOrder(_) => Self::NEW_ORDER,
+            Self::Market(_) => Self::MARKET,
+            Self::Unknown(value) => value["msg_type"].as_i64().unwrap_or_default(),
+        }
+    }
+}
+
+impl Response {
+    /// Parses every entry of `messages`, in the order the server sent them.
+    pub fn parse_messages(&self) -> Vec<Message> {
+        self.messages
+            .iter()
+            .cloned()
+            .map(Message::from_value)
+            .collect()
+    }
+
+    pub fn into_messages(self) -> Vec<Message> {
+        self.messages.into_iter().map(Message::from_value).collect()
+    }
+}
+
+impl FromResponse for Vec<Message> {
+    fn from_response(resp: Response) -> WeLoveResult<Self> {
+        Ok(resp.into_messages())
+    }
+}
+
+crate::from_message!(Vec<Field> => Fields, 2);
+crate::from_message!(Vec<Warehouse> => Warehouses, 3);
+crate::from_message!(OrderInfo => Orders, 15);
+crate::from_message!(StallInfo => Stall, 20);
+crate::from_message!(Order => NewOrder, 47);
+crate::from_message!(MarketInfo => Market, 920);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::order::OrderApi;
+    use crate::api::tests::get_mock_client;
+    use serde_json::json;
+
+    #[test]
+    fn test_message_from_value() {
+        let msg = Message::from_value(
+            json!({"msg_type": 2, "fields": [{"id": 1, "plant_item_id": 201001}]}),
+        );
+        assert!(matches!(&msg, Message::Fields(fields) if fields[0].plant_item_id == 201001));
+        let msg = Message::from_value(json!({"msg_type": 12345, "foo": 1}));
+        assert_eq!(msg.msg_type(), 12345);
+        assert!(matches!(msg, Message::Unknown(_)));
+        let msg = Message::from_value(json!({"msg_type": 2, "fields": "none"}));
+        assert_eq!(msg.msg_type(), Message::FIELDS);
+        assert!(matches!(msg, Message::Unknown(_)));
+    }
+
+    #[tokio::test]
+    async fn test_accomplish_messages() {
+        let (cli, _) = get_mock_client();
+        let messages = cli
+            .order_accomplish(2434433066, false)
+            .await
+            .unwrap()
+            .into_messages();
+        assert!(messages.iter().any(|m| matches!(m, Message::Warehouses(_))));
+        assert!(messages.iter().any(|m| matches!(m, Message::NewOrder(_))));
+    }
+}
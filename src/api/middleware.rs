@@ -0,0 +1,129 @@
+use super::Response;
+use crate::error::WeLoveResult;
+use std::collections::HashMap;
+
+/// A layer around every request sent by [`WeLoveClient::post`](super::WeLoveClient::post).
+///
+/// Layers run `before_send` in the order they were added and `after_receive` in reverse order.
+/// Params rewritten in `before_send` are signed again before the request is sent, and
+/// `after_receive` sees the decoded response before a non-zero `result` becomes an error.
+/// Returning an error from either hook fails the request.
+pub trait Middleware: Send + Sync {
+    fn before_send(&self, _path: &str, _params: &mut HashMap<String, String>) -> WeLoveResult<()> {
+        Ok(())
+    }
+
+    fn after_receive(&self, _path: &str, _resp: &mut Response) -> WeLoveResult<()> {
+        Ok(())
+    }
+}
+
+/// Logs every request and its result at debug level.
+pub struct TracingMiddleware;
+
+impl Middleware for TracingMiddleware {
+    fn before_send(&self, path: &str, params: &mut HashMap<String, String>) -> WeLoveResult<()> {
+        tracing::debug!("send {path}, params: {params:?}");
+        Ok(())
+    }
+
+    fn after_receive(&self, path: &str, resp: &mut Response) -> WeLoveResult<()> {
+        tracing::debug!(
+            "receive {path}, result: {}, error_msg: {}, messages: {}",
+            resp.result,
+            resp.error_msg,
+            resp.messages.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::market::MarketApi;
+    use crate::api::panorama::PanoramaApi;
+    use crate::api::tests::get_mock_client;
+    use crate::error::WeLoveError;
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Recorder {
+        fn before_send(
+            &self,
+            path: &str,
+            params: &mut HashMap<String, String>,
+        ) -> WeLoveResult<()> {
+            assert!(params.contains_key("sig"));
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("{} before {path}", self.0));
+            Ok(())
+        }
+
+        fn after_receive(&self, path: &str, _resp: &mut Response) -> WeLoveResult<()> {
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("{} after {path}", self.0));
+            Ok(())
+        }
+    }
+
+    struct HalveCount;
+
+    impl Middleware for HalveCount {
+        fn before_send(
+            &self,
+            _path: &str,
+            params: &mut HashMap<String, String>,
+        ) -> WeLoveResult<()> {
+            if let Some(count) = params.get_mut("count") {
+                *count = (count.parse::<i64>().unwrap() / 2).to_string();
+            }
+            Ok(())
+        }
+    }
+
+    struct FailAll;
+
+    impl Middleware for FailAll {
+        fn after_receive(&self, _path: &str, resp: &mut Response) -> WeLoveResult<()> {
+            resp.result = 500;
+            resp.error_msg = "injected".to_string();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (mut cli, _) = get_mock_client();
+        cli.middlewares.push(Box::new(Recorder("a", log.clone())));
+        cli.middlewares.push(Box::new(Recorder("b", log.clone())));
+        cli.panorama().await.unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "a before /v1/game/farm/panorama",
+                "b before /v1/game/farm/panorama",
+                "b after /v1/game/farm/panorama",
+                "a after /v1/game/farm/panorama",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rewrite_and_fault() {
+        let (mut cli, farm) = get_mock_client();
+        cli.middlewares.push(Box::new(HalveCount));
+        cli.market_sale(201001, 10).await.unwrap();
+        assert_eq!(farm.lock().unwrap().warehouse[&201001], 25);
+
+        cli.middlewares.push(Box::new(FailAll));
+        let err = cli.market_query().await.unwrap_err();
+        assert!(matches!(err, WeLoveError::Server { result: 500, .. }));
+    }
+}
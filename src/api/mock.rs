@@ -0,0 +1,377 @@
+//! In-process mock of the `/v1/game/farm/*` routes, so the api tests can run without a live account.
+//!
+//! The routes are answered by a [`SimFarm`] seeded with the ids the api tests use. Its
+//! clock follows the wall clock, but nothing happens on its own: listings never sell
+//! and refused orders are not replaced.
+
+use super::market::{MarketInfo, MarketItem};
+use super::order::{Order, OrderItem};
+use super::panorama::Field;
+use super::stall::{StallInfo, StallItem};
+use super::Response;
+use crate::catalog::tests::test_catalog;
+use crate::sim::{SimConfig, SimFarm, SimResult};
+use crate::utils::sig::calculate_sig;
+use crate::utils::unix_now;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request};
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+pub const RESULT_SIG_ERROR: u32 = 1000;
+pub const RESULT_SESSION_ERROR: u32 = 1001;
+pub const RESULT_NOT_FOUND: u32 = 2004;
+
+pub struct MockServer {
+    pub base_url: String,
+    pub farm: Arc<Mutex<SimFarm>>,
+}
+
+impl MockServer {
+    /// Starts a server with a fresh [`mock_farm`] on its own thread, so it outlives the runtime of a single test.
+    pub fn start() -> Self {
+        let farm = Arc::new(Mutex::new(mock_farm()));
+        let listener =
+            std::net::TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        listener.set_nonblocking(true).unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server_farm = farm.clone();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            rt.block_on(async move {
+                let make_service = make_service_fn(move |_| {
+                    let farm = server_farm.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| handle(farm.clone(), req)))
+                    }
+                });
+                hyper::Server::from_tcp(listener)
+                    .unwrap()
+                    .serve(make_service)
+                    .await
+                    .unwrap();
+            });
+        });
+        Self { base_url, farm }
+    }
+}
+
+async fn handle(
+    farm: Arc<Mutex<SimFarm>>,
+    req: Request<Body>,
+) -> Result<hyper::Response<Body>, Infallible> {
+    let path = req.uri().path().to_string();
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .unwrap_or_default();
+    let params: HashMap<String, String> = serde_urlencoded::from_bytes(&body).unwrap_or_default();
+    let resp = match dispatch(&mut farm.lock().unwrap(), &path, &params) {
+        Ok(messages) => Response {
+            result: 0,
+            messages,
+            error_msg: String::new(),
+        },
+        Err((result, error_msg)) => Response {
+            result,
+            messages: Vec::new(),
+            error_msg,
+        },
+    };
+    Ok(hyper::Response::new(Body::from(
+        serde_json::to_vec(&resp).unwrap(),
+    )))
+}
+
+fn param<'a>(params: &'a HashMap<String, String>, key: &str) -> Result<&'a str, (u32, String)> {
+    params
+        .get(key)
+        .map(String::as_str)
+        .ok_or((RESULT_NOT_FOUND, format!("missing param {key}")))
+}
+
+fn param_i64(params: &HashMap<String, String>, key: &str) -> Result<i64, (u32, String)> {
+    param(params, key)?
+        .parse()
+        .map_err(|_| (RESULT_NOT_FOUND, format!("invalid param {key}")))
+}
+
+/// The farm behind [`MockServer`]. The seed data contains the ids the api tests use.
+pub fn mock_farm() -> SimFarm {
+    let now = unix_now();
+    let mut farm = SimFarm::new(
+        SimConfig {
+            start_time: now,
+            fields: 0,
+            order_slots: 0,
+            ..Default::default()
+        },
+        test_catalog(),
+    );
+    let field = |id: i64, x: i64, y: i64, plant_item_id: i64, plant_time: i64| Field {
+        id,
+        x,
+        y,
+        item_id: 101001,
+        plant_item_id,
+        plant_time,
+        ..Default::default()
+    };
+    let order = |order_id: i64, slot: i64, items: Vec<OrderItem>, time_left: i64| Order {
+        order_id,
+        slot,
+        items,
+        time_left,
+        coin: 40,
+        exp: 6,
+        ..Default::default()
+    };
+    farm.coin = 1000;
+    farm.fields = vec![
+        field(1, 10, 10, 201001, now - 600),
+        field(2, 10, 11, 201001, now - 600),
+        field(3, 11, 10, 201001, now),
+        field(4, 11, 11, 201002, now - 600),
+        field(5, 12, 10, -1, 0),
+    ];
+    farm.warehouse = HashMap::from([(201001, 30), (201002, 5), (209001, 2)]);
+    farm.orders = vec![
+        order(
+            2434433066,
+            1,
+            vec![OrderItem {
+                item_id: 201001,
+                count: 2,
+            }],
+            -1,
+        ),
+        order(
+            2434433067,
+            2,
+            vec![
+                OrderItem {
+                    item_id: 201001,
+                    count: 2,
+                },
+                OrderItem {
+                    item_id: 201002,
+                    count: 3,
+                },
+            ],
+            -1,
+        ),
+        Order {
+            status: 1,
+            ..order(2434423817, 3, vec![], 0)
+        },
+        order(0, 4, vec![], 300),
+    ];
+    farm.slot_ready_at = HashMap::from([(4, now + 300)]);
+    farm.market = MarketInfo {
+        op_time: now,
+        msg_type: 920,
+        market_item_list: [
+            (1, 201001, 10),
+            (2, 209001, 1),
+            (3, 209002, 1),
+            (4, 210001, 1),
+        ]
+        .into_iter()
+        .map(|(id, item_id, count)| MarketItem {
+            id,
+            item_id,
+            count,
+            sold_out: 0,
+            coin: 60,
+        })
+        .collect(),
+        next_refresh_time: now + 3600,
+    };
+    farm.stall = StallInfo {
+        op_time: now,
+        msg_type: 20,
+        stall_items: vec![
+            StallItem {
+                id: 3226224501,
+                slot: 1,
+                item_id: 201001,
+                count: 10,
+                coin: 36,
+                status: 1,
+                ..Default::default()
+            },
+            StallItem {
+                id: 3226224553,
+                slot: 3,
+                item_id: 201001,
+                count: 10,
+                coin: 36,
+                status: 2,
+                buyer_farm_name: "mock buyer".to_string(),
+                ..Default::default()
+            },
+        ],
+        capacity: 4,
+        farm_id: "3226224553".to_string(),
+        ..Default::default()
+    };
+    farm
+}
+
+/// Checks the session params, then answers `path` from `farm`.
+pub fn dispatch(farm: &mut SimFarm, path: &str, params: &HashMap<String, String>) -> SimResult {
+    let borrowed: HashMap<&str, &str> = params
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    if param(params, "sig")? != calculate_sig("POST", path, &borrowed) {
+        return Err((RESULT_SIG_ERROR, "sig error".to_string()));
+    }
+    if params.get("union_id").is_none_or(String::is_empty) {
+        return Err((RESULT_SESSION_ERROR, "session invalid".to_string()));
+    }
+    if params.get("fv").is_none_or(String::is_empty) {
+        return Err((RESULT_SESSION_ERROR, "version too old".to_string()));
+    }
+    farm.now = unix_now();
+    let p = |key| param_i64(params, key);
+    match path.strip_prefix("/v1/game/farm").unwrap_or_default() {
+        "/panorama" => {
+            let mut messages = farm.panorama();
+            messages.push(json!({ "msg_type": 30, "weather": "sunny" }));
+            Ok(messages)
+        }
+        "/crops/plant" => {
+            let farmlands = serde_json::from_str(param(params, "farmlands")?)
+                .map_err(|e| (RESULT_NOT_FOUND, e.to_string()))?;
+            farm.crops_plant(p("item_id")?, farmlands)
+        }
+        "/crops/harvest" => {
+            let farmland_ids = param(params, "farmland_ids")?
+                .split(',')
+                .filter_map(|id| id.parse().ok())
+                .collect();
+            farm.crops_harvest(p("item_id")?, farmland_ids)
+        }
+        "/market/sale" => farm.market_sale(p("item_id")?, p("count")?),
+        "/market/query" => Ok(vec![farm.market_message()]),
+        "/market/buy" => farm.market_buy(p("id")?),
+        "/order/query" => Ok(vec![farm.orders_message()]),
+        "/order/refuse" => farm.order_refuse(p("order_id")?),
+        "/order/accomplish" => farm.order_accomplish(p("order_id")?),
+        "/order/reward" => farm.order_reward(p("order_id")?),
+        "/stall/query" => Ok(vec![farm.stall_message()]),
+        "/stall/earn" => farm.stall_earn(p("slot")?, p("stall_sale_id")?),
+        // Other farms are not simulated, so buying from one always gets 10 wheat.
+        "/stall/buy" => {
+            p("stall_sale_id")?;
+            p("seller_farm_id")?;
+            *farm.warehouse.entry(201001).or_default() += 10;
+            Ok(vec![farm.warehouses_message()])
+        }
+        "/stall/onshelf" => {
+            param(params, "check")?;
+            farm.stall_onshelf(
+                p("slot")?,
+                p("item_id")?,
+                p("count")?,
+                p("coin")?,
+                p("ad")? == 1,
+            )
+        }
+        _ => Err((RESULT_NOT_FOUND, format!("unknown path {path}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::panorama::PanoramaApi;
+    use crate::api::tests::get_mock_client;
+    use crate::api::WeLoveClient;
+    use crate::error::WeLoveError;
+
+    #[tokio::test]
+    async fn test_mock_rejects_bad_sig() {
+        let server = MockServer::start();
+        let resp: Response = reqwest::Client::new()
+            .post(format!("{}/v1/game/farm/panorama", server.base_url))
+            .form(&HashMap::from([("ts", "1"), ("sig", "bad")]))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(resp.result, RESULT_SIG_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_mock_panorama() {
+        let (cli, _) = get_mock_client();
+        let fields = cli.get_fields().await.unwrap();
+        assert_eq!(fields.len(), 5);
+        assert!(fields
+            .iter()
+            .any(|f| f.plant_item_id == 201001 && f.left_time < 0));
+        assert!(fields.iter().any(|f| f.plant_item_id == -1));
+        let warehouses = cli.get_warehouses().await.unwrap();
+        assert!(warehouses.iter().any(|w| w.category == 1));
+        let snapshot = cli.snapshot().await.unwrap();
+        assert_eq!(snapshot.warehouse_items()[&201001], 30);
+        assert_eq!(snapshot.others.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mock_harvest_and_accomplish() {
+        use crate::api::crops::CropsApi;
+        use crate::api::order::OrderApi;
+
+        let (cli, farm) = get_mock_client();
+        cli.crops_harvest(201001, vec![1, 2]).await.unwrap();
+        assert_eq!(farm.lock().unwrap().warehouse[&201001], 34);
+
+        let resp = cli.order_accomplish(2434433066, false).await.unwrap();
+        assert_eq!(resp.result, 0);
+        assert!(resp.messages.iter().any(|m| m["msg_type"] == 47));
+        assert_eq!(farm.lock().unwrap().warehouse[&201001], 32);
+
+        let err = cli.order_accomplish(2434433066, false).await.unwrap_err();
+        assert!(matches!(err, WeLoveError::Server { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_mock_errors() {
+        use crate::api::stall::StallApi;
+
+        let (cli, _) = get_mock_client();
+        let err = cli
+            .stall_onshelf(1, 201001, 10, 36, false, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WeLoveError::Server { .. }));
+        let err = cli
+            .stall_onshelf(2, 201001, 100, 36, false, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WeLoveError::Server { .. }));
+
+        let server = MockServer::start();
+        let cli = WeLoveClient::builder()
+            .base_url(server.base_url)
+            .build()
+            .unwrap();
+        let err = cli.panorama().await.unwrap_err();
+        assert!(matches!(
+            err,
+            WeLoveError::Server {
+                result: RESULT_SESSION_ERROR,
+                ..
+            }
+        ));
+    }
+}
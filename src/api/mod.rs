@@ -1,13 +1,35 @@
+pub mod builder;
+pub mod cassette;
 pub mod crops;
+pub mod endpoint;
 pub mod market;
+pub mod message;
+pub mod middleware;
+#[cfg(test)]
+pub mod mock;
 pub mod order;
 pub mod panorama;
+pub mod rate_limit;
+pub mod retry;
 pub mod stall;
 
 use crate::error::{WeLoveError, WeLoveResult};
+use builder::WeLoveClientBuilder;
+use cassette::{Cassette, CassetteMode};
+use middleware::Middleware;
+use rate_limit::RateLimiter;
+use retry::{RetryConfig, RetryPolicy};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Paths that only read farm state: the only ones a dry run sends, and retried by default.
+pub const READ_ONLY_PATHS: [&str; 4] = [
+    "/v1/game/farm/panorama",
+    "/v1/game/farm/order/query",
+    "/v1/game/farm/stall/query",
+    "/v1/game/farm/market/query",
+];
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Response {
@@ -16,26 +38,66 @@ pub struct Response {
     pub error_msg: String,
 }
 
+/// Extracts a typed result from a decoded [`Response`].
+pub trait FromResponse: Sized {
+    fn from_response(resp: Response) -> WeLoveResult<Self>;
+}
+
+impl FromResponse for Response {
+    fn from_response(resp: Response) -> WeLoveResult<Self> {
+        Ok(resp)
+    }
+}
+
+impl<A: FromResponse, B: FromResponse> FromResponse for (A, B) {
+    fn from_response(resp: Response) -> WeLoveResult<Self> {
+        Ok((A::from_response(resp.clone())?, B::from_response(resp)?))
+    }
+}
+
 pub struct WeLoveClient {
     pub http_client: reqwest::Client,
     pub base_url: String,
     pub default_params: HashMap<String, String>,
+    pub cassette: Option<Cassette>,
+    pub retry: RetryConfig,
+    pub rate_limiter: RateLimiter,
+    pub middlewares: Vec<Box<dyn Middleware>>,
+    /// Only query paths are sent. Every other request is logged and answered with
+    /// an empty successful [`Response`].
+    pub dry_run: bool,
 }
 
 impl WeLoveClient {
+    #[deprecated(note = "use `WeLoveClient::builder()`, which reports bad settings as errors")]
     pub fn new(
         base_url: String,
         default_params: HashMap<String, String>,
         default_headers: HashMap<String, String>,
     ) -> Self {
-        Self {
-            http_client: reqwest::ClientBuilder::new()
-                .default_headers((&default_headers).try_into().unwrap())
-                .build()
-                .unwrap(),
-            base_url,
-            default_params,
+        let mut builder = Self::builder().base_url(base_url);
+        for (k, v) in default_params {
+            builder = builder.param(k, v);
+        }
+        for (k, v) in default_headers {
+            builder = builder.header(k, v);
         }
+        builder.build().unwrap()
+    }
+
+    pub fn builder() -> WeLoveClientBuilder {
+        Default::default()
+    }
+
+    /// Records every exchange to, or replays every exchange from, `cassette`.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
     }
 
     pub async fn post(
@@ -43,45 +105,167 @@ impl WeLoveClient {
         path: &str,
         mut params: HashMap<&str, &str>,
     ) -> WeLoveResult<Response> {
+        if self.dry_run && !READ_ONLY_PATHS.contains(&path) {
+            let params: std::collections::BTreeMap<_, _> = params.into_iter().collect();
+            tracing::info!("dry run {path}, params: {params:?}");
+            return Ok(Response::default());
+        }
         for (k, v) in self.default_params.iter() {
             params.insert(k, v);
         }
+        let policy = self.retry.policy_for(path);
+        let mut attempt = 1;
+        loop {
+            match self.send(path, params.clone()).await {
+                Err(err) if attempt < policy.max_attempts && RetryPolicy::is_transient(&err) => {
+                    let delay = policy.delay(attempt);
+                    tracing::warn!("retry {path} in {delay:?}, attempt: {attempt}, err: {err}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a typed request, flattened into form params by [`to_form`](crate::utils::form::to_form).
+    pub async fn post_typed<Req: Serialize, Resp: FromResponse>(
+        &self,
+        path: &str,
+        req: &Req,
+    ) -> WeLoveResult<Resp> {
+        let params = crate::utils::form::to_form(req)?;
+        Resp::from_response(
+            self.post(
+                path,
+                params
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect(),
+            )
+            .await?,
+        )
+    }
+
+    /// Signs `params` with a fresh timestamp and sends them once.
+    async fn send(&self, path: &str, params: HashMap<&str, &str>) -> WeLoveResult<Response> {
+        let mut params: HashMap<String, String> = params
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
         let timestamp = std::time::SystemTime::UNIX_EPOCH
             .elapsed()
             .unwrap()
             .as_millis()
             .to_string();
-        params.insert("ts", &timestamp);
-        let sig = crate::utils::sig::calculate_sig("POST", path, &params);
-        params.insert("sig", &sig);
-        self.http_client
-            .post(format!("{}{path}", self.base_url))
-            .form(&params)
-            .send()
-            .await?
-            .json()
-            .await
-            .map_err(WeLoveError::from)
+        params.insert("ts".to_string(), timestamp);
+        Self::sign(path, &mut params);
+        if !self.middlewares.is_empty() {
+            for middleware in self.middlewares.iter() {
+                middleware.before_send(path, &mut params)?;
+            }
+            Self::sign(path, &mut params);
+        }
+        let params: HashMap<&str, &str> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+
+        let mut resp = match self.cassette.as_ref() {
+            Some(cassette) if cassette.mode == CassetteMode::Replay => {
+                serde_json::from_str(&cassette.find(path, &params)?)?
+            }
+            _ => {
+                self.rate_limiter.acquire(path).await;
+                let text = self
+                    .http_client
+                    .post(format!("{}{path}", self.base_url))
+                    .form(&params)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?;
+                if let Some(cassette) = self.cassette.as_ref() {
+                    cassette.save(path, &params, &text)?;
+                }
+                serde_json::from_str(&text)?
+            }
+        };
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after_receive(path, &mut resp)?;
+        }
+        Self::check_result(resp)
+    }
+
+    fn sign(path: &str, params: &mut HashMap<String, String>) {
+        let sig = crate::utils::sig::calculate_sig(
+            "POST",
+            path,
+            &params
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+        );
+        params.insert("sig".to_string(), sig);
+    }
+
+    /// Turns a response with a non-zero `result` into [`WeLoveError::Server`].
+    fn check_result(resp: Response) -> WeLoveResult<Response> {
+        match resp.result {
+            0 => Ok(resp),
+            result => Err(WeLoveError::Server {
+                result,
+                msg: resp.error_msg,
+            }),
+        }
     }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use crate::api::mock::MockServer;
     use crate::api::WeLoveClient;
-    use std::collections::HashMap;
+    use crate::sim::SimFarm;
+    use std::sync::{Arc, Mutex};
 
+    /// Uses the live account from `BASE_URL`/`VERSION`/`UNION_ID` when `BASE_URL` is set, otherwise a fresh mock server.
     pub fn get_test_client() -> WeLoveClient {
-        let base_url = std::env::var("BASE_URL").expect("env BASE_URL is not set");
+        let Ok(base_url) = std::env::var("BASE_URL") else {
+            return get_mock_client().0;
+        };
         let version = std::env::var("VERSION").expect("env VERSION is not set");
         let union_id = std::env::var("UNION_ID").expect("env UNION_ID is not set");
 
-        WeLoveClient::new(
-            base_url,
-            HashMap::from([
-                ("fv".to_string(), version),
-                ("union_id".to_string(), union_id),
-            ]),
-            Default::default(),
-        )
+        WeLoveClient::builder()
+            .base_url(base_url)
+            .fv(version)
+            .union_id(union_id)
+            .build()
+            .unwrap()
+    }
+
+    pub fn get_mock_client() -> (WeLoveClient, Arc<Mutex<SimFarm>>) {
+        let server = MockServer::start();
+        let cli = WeLoveClient::builder()
+            .base_url(server.base_url)
+            .fv("mock")
+            .union_id("mock")
+            .build()
+            .unwrap();
+        (cli, server.farm)
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        use crate::api::market::MarketApi;
+        use crate::api::panorama::PanoramaApi;
+
+        let (mut cli, farm) = get_mock_client();
+        cli.dry_run = true;
+        let resp = cli.market_sale(201001, 10).await.unwrap();
+        assert!(resp.messages.is_empty());
+        assert_eq!(farm.lock().unwrap().warehouse[&201001], 30);
+        assert_eq!(cli.snapshot().await.unwrap().warehouse_items()[&201001], 30);
     }
 }
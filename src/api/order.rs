@@ -1,6 +1,5 @@
-use crate::error::{WeLoveError, WeLoveResult};
+use crate::error::WeLoveResult;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 use super::{Response, WeLoveClient};
 pub trait OrderApi {
@@ -48,25 +47,30 @@ pub struct OrderItem {
     pub count: i64,
 }
 
+crate::endpoint! {
+    pub struct OrderQueryRequest {} => "/v1/game/farm/order/query" -> OrderInfo;
+
+    pub struct OrderRefuseRequest {
+        pub order_id: i64,
+    } => "/v1/game/farm/order/refuse" -> Response;
+
+    pub struct OrderAccomplishRequest {
+        pub order_id: i64,
+        pub by_rainbow_coin: bool,
+    } => "/v1/game/farm/order/accomplish" -> Response;
+
+    pub struct OrderRewardRequest {
+        pub order_id: i64,
+    } => "/v1/game/farm/order/reward" -> Response;
+}
+
 impl OrderApi for WeLoveClient {
     async fn order_query(&self) -> WeLoveResult<OrderInfo> {
-        serde_json::from_value(
-            self.post("/v1/game/farm/order/query", Default::default())
-                .await?
-                .messages
-                .into_iter()
-                .find(|m| m["msg_type"] == 15)
-                .ok_or(WeLoveError::None("failed to get message msg_type=15"))?,
-        )
-        .map_err(WeLoveError::from)
+        self.call(&OrderQueryRequest {}).await
     }
 
     async fn order_refuse(&self, order_id: i64) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/order/refuse",
-            HashMap::from([("order_id", order_id.to_string().as_str())]),
-        )
-        .await
+        self.call(&OrderRefuseRequest { order_id }).await
     }
 
     async fn order_accomplish(
@@ -74,25 +78,15 @@ impl OrderApi for WeLoveClient {
         order_id: i64,
         by_rainbow_coin: bool,
     ) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/order/accomplish",
-            HashMap::from([
-                ("order_id", order_id.to_string().as_str()),
-                (
-                    "by_rainbow_coin",
-                    (by_rainbow_coin as i32).to_string().as_str(),
-                ),
-            ]),
-        )
+        self.call(&OrderAccomplishRequest {
+            order_id,
+            by_rainbow_coin,
+        })
         .await
     }
 
     async fn order_reward(&self, order_id: i64) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/order/reward",
-            HashMap::from([("order_id", order_id.to_string().as_str())]),
-        )
-        .await
+        self.call(&OrderRewardRequest { order_id }).await
     }
 }
 
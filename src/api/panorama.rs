@@ -1,12 +1,20 @@
-use crate::error::{WeLoveError, WeLoveResult};
+use crate::error::WeLoveResult;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use super::{Response, WeLoveClient};
+use super::endpoint::Endpoint;
+use super::message::Message;
+use super::{FromResponse, Response, WeLoveClient};
 
 pub trait PanoramaApi {
     async fn panorama(&self) -> WeLoveResult<Response>;
     async fn get_fields(&self) -> WeLoveResult<Vec<Field>>;
     async fn get_warehouses(&self) -> WeLoveResult<Vec<Warehouse>>;
+
+    /// Fetches the panorama once and keeps every message of it.
+    async fn snapshot(&self) -> WeLoveResult<FarmSnapshot> {
+        FarmSnapshot::from_response(self.panorama().await?)
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +39,17 @@ pub struct Warehouse {
     pub items: Vec<ItemInfo>,
 }
 
+impl Warehouse {
+    /// Item counts keyed by item_id, across all categories.
+    pub fn item_counts(warehouses: &[Warehouse]) -> HashMap<i64, i64> {
+        warehouses
+            .iter()
+            .flat_map(|w| w.items.iter())
+            .map(|item| (item.item_id, item.count))
+            .collect()
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ItemInfo {
@@ -38,36 +57,56 @@ pub struct ItemInfo {
     pub item_id: i64,
 }
 
+/// Everything one panorama call returned, taken at the same moment.
+#[derive(Default, Debug, Clone)]
+pub struct FarmSnapshot {
+    pub fields: Vec<Field>,
+    pub warehouses: Vec<Warehouse>,
+    /// Messages without a dedicated part above, in the order they were received.
+    pub others: Vec<Message>,
+}
+
+impl FarmSnapshot {
+    pub fn from_messages(messages: Vec<Message>) -> Self {
+        let mut snapshot = Self::default();
+        for message in messages {
+            match message {
+                Message::Fields(fields) => snapshot.fields = fields,
+                Message::Warehouses(warehouses) => snapshot.warehouses = warehouses,
+                message => snapshot.others.push(message),
+            }
+        }
+        snapshot
+    }
+
+    pub fn warehouse_items(&self) -> HashMap<i64, i64> {
+        Warehouse::item_counts(&self.warehouses)
+    }
+}
+
+impl FromResponse for FarmSnapshot {
+    fn from_response(resp: Response) -> WeLoveResult<Self> {
+        Ok(Self::from_messages(resp.into_messages()))
+    }
+}
+
+crate::endpoint! {
+    pub struct PanoramaRequest {} => "/v1/game/farm/panorama" -> Response;
+}
+
 impl PanoramaApi for WeLoveClient {
     async fn panorama(&self) -> WeLoveResult<Response> {
-        self.post("/v1/game/farm/panorama", Default::default())
-            .await
+        self.call(&PanoramaRequest {}).await
     }
 
     async fn get_fields(&self) -> WeLoveResult<Vec<Field>> {
-        serde_json::from_value(
-            self.panorama()
-                .await?
-                .messages
-                .into_iter()
-                .find(|m| m["msg_type"] == 2)
-                .ok_or(WeLoveError::None("failed to get message msg_type=2"))?["fields"]
-                .take(),
-        )
-        .map_err(WeLoveError::from)
+        self.post_typed(PanoramaRequest::PATH, &PanoramaRequest {})
+            .await
     }
 
     async fn get_warehouses(&self) -> WeLoveResult<Vec<Warehouse>> {
-        serde_json::from_value(
-            self.panorama()
-                .await?
-                .messages
-                .into_iter()
-                .find(|m| m["msg_type"] == 3)
-                .ok_or(WeLoveError::None("failed to get message msg_type=3"))?["warehouses"]
-                .take(),
-        )
-        .map_err(WeLoveError::from)
+        self.post_typed(PanoramaRequest::PATH, &PanoramaRequest {})
+            .await
     }
 }
 
@@ -103,10 +142,18 @@ mod tests {
         println!("{resp:?}");
         let items: HashMap<_, _> = resp
             .into_iter()
-            .map(|w| w.items)
-            .flatten()
+            .flat_map(|w| w.items)
             .map(|item| (item.item_id, item.count))
             .collect();
         println!("{:?}", items)
     }
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        let cli = get_test_client();
+        let snapshot = cli.snapshot().await.unwrap();
+        println!("{snapshot:?}");
+        assert!(!snapshot.fields.is_empty());
+        assert!(!snapshot.warehouses.is_empty());
+    }
 }
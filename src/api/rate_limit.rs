@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A token bucket refilled at `rate` tokens per second, holding at most `burst` tokens.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self {
+            rate: rate.max(0.001),
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Takes a token, waiting until one is available. Waiters are served in arrival order.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let (tokens, last) = &mut *state;
+            let now = Instant::now();
+            *tokens =
+                (*tokens + now.duration_since(*last).as_secs_f64() * self.rate).min(self.burst);
+            *last = now;
+            *tokens -= 1.0;
+            if *tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64(-*tokens / self.rate)
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Paces requests with a global bucket and optional per-path buckets. A request waits for both.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    pub global: Option<TokenBucket>,
+    pub paths: HashMap<String, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn global(mut self, rate: f64, burst: u32) -> Self {
+        self.global = Some(TokenBucket::new(rate, burst));
+        self
+    }
+
+    pub fn path(mut self, path: &str, rate: f64, burst: u32) -> Self {
+        self.paths
+            .insert(path.to_string(), TokenBucket::new(rate, burst));
+        self
+    }
+
+    pub async fn acquire(&self, path: &str) {
+        if let Some(bucket) = self.paths.get(path) {
+            bucket.acquire().await;
+        }
+        if let Some(bucket) = self.global.as_ref() {
+            bucket.acquire().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_bucket() {
+        let bucket = TokenBucket::new(10.0, 2);
+        let start = Instant::now();
+        for _ in 0..4 {
+            bucket.acquire().await;
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(190), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(400), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_path() {
+        let limiter =
+            RateLimiter::new()
+                .global(1000.0, 10)
+                .path("/v1/game/farm/stall/onshelf", 10.0, 1);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire("/v1/game/farm/stall/query").await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+        for _ in 0..3 {
+            limiter.acquire("/v1/game/farm/stall/onshelf").await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(190));
+    }
+}
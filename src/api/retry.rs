@@ -0,0 +1,183 @@
+use super::READ_ONLY_PATHS;
+use crate::error::WeLoveError;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` disables retrying.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of the delay that is randomized, in `0.0..=1.0`.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the attempt following `attempt` (1-based): `base_delay * 2^(attempt-1)`, capped and jittered.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - jitter + rand::thread_rng().gen_range(0.0..=2.0 * jitter))
+    }
+
+    /// Only errors that happened before a response was received are retried.
+    pub fn is_transient(err: &WeLoveError) -> bool {
+        match err {
+            WeLoveError::Reqwest(err) => err.is_timeout() || err.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+/// Decides which [`RetryPolicy`] applies to a path.
+///
+/// Paths in `safe_paths` use `policy`, paths in `endpoints` use their own policy,
+/// and every other path (the mutating calls) is sent only once.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub policy: RetryPolicy,
+    pub safe_paths: HashSet<String>,
+    pub endpoints: HashMap<String, RetryPolicy>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            policy: Default::default(),
+            safe_paths: READ_ONLY_PATHS.iter().map(|p| p.to_string()).collect(),
+            endpoints: Default::default(),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Never retries anything.
+    pub fn disabled() -> Self {
+        Self {
+            policy: RetryPolicy::none(),
+            safe_paths: Default::default(),
+            endpoints: Default::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Marks a mutating path as safe to retry with the default policy.
+    pub fn mark_safe(mut self, path: &str) -> Self {
+        self.safe_paths.insert(path.to_string());
+        self
+    }
+
+    pub fn with_endpoint(mut self, path: &str, policy: RetryPolicy) -> Self {
+        self.endpoints.insert(path.to_string(), policy);
+        self
+    }
+
+    pub fn policy_for(&self, path: &str) -> RetryPolicy {
+        if let Some(policy) = self.endpoints.get(path) {
+            policy.clone()
+        } else if self.safe_paths.contains(path) {
+            self.policy.clone()
+        } else {
+            RetryPolicy::none()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::panorama::PanoramaApi;
+    use crate::api::stall::StallApi;
+    use crate::api::WeLoveClient;
+
+    #[test]
+    fn test_policy_for() {
+        let config = RetryConfig::default()
+            .mark_safe("/v1/game/farm/stall/earn")
+            .with_endpoint("/v1/game/farm/market/query", RetryPolicy::none());
+        assert_eq!(config.policy_for("/v1/game/farm/panorama").max_attempts, 3);
+        assert_eq!(
+            config.policy_for("/v1/game/farm/stall/earn").max_attempts,
+            3
+        );
+        assert_eq!(
+            config
+                .policy_for("/v1/game/farm/stall/onshelf")
+                .max_attempts,
+            1
+        );
+        assert_eq!(
+            config.policy_for("/v1/game/farm/market/query").max_attempts,
+            1
+        );
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+        assert_eq!(policy.delay(10), Duration::from_secs(8));
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(800) && delay <= Duration::from_millis(1200));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_connect_error() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let cli = WeLoveClient::builder()
+            .base_url("http://127.0.0.1:1")
+            .retry(RetryConfig::default().with_policy(policy))
+            .build()
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        assert!(cli.panorama().await.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(300));
+
+        let start = std::time::Instant::now();
+        assert!(cli.stall_earn(1, 1).await.is_err());
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -1,7 +1,5 @@
-use std::collections::HashMap;
-
 use super::{Response, WeLoveClient};
-use crate::error::{WeLoveError, WeLoveResult};
+use crate::error::WeLoveResult;
 use rand::distributions::DistString;
 use serde::{Deserialize, Serialize};
 
@@ -47,38 +45,49 @@ pub struct StallItem {
     pub coin: i64,
 }
 
+crate::endpoint! {
+    pub struct StallQueryRequest {} => "/v1/game/farm/stall/query" -> StallInfo;
+
+    pub struct StallEarnRequest {
+        pub slot: i64,
+        pub stall_sale_id: i64,
+    } => "/v1/game/farm/stall/earn" -> Response;
+
+    pub struct StallBuyRequest {
+        pub stall_sale_id: i64,
+        pub seller_farm_id: i64,
+    } => "/v1/game/farm/stall/buy" -> Response;
+
+    pub struct StallOnshelfRequest {
+        pub slot: i64,
+        pub item_id: i64,
+        pub count: i64,
+        pub coin: i64,
+        pub ad: bool,
+        pub rainbow_coin: i64,
+        /// Random 6-character nonce.
+        pub check: String,
+    } => "/v1/game/farm/stall/onshelf" -> Response;
+}
+
 impl StallApi for WeLoveClient {
     async fn stall_query(&self) -> WeLoveResult<StallInfo> {
-        serde_json::from_value(
-            self.post("/v1/game/farm/stall/query", Default::default())
-                .await?
-                .messages
-                .into_iter()
-                .find(|m| m["msg_type"] == 20)
-                .ok_or(WeLoveError::None("failed to get message msg_type=20"))?,
-        )
-        .map_err(WeLoveError::from)
+        self.call(&StallQueryRequest {}).await
     }
 
     async fn stall_earn(&self, slot: i64, stall_sale_id: i64) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/stall/earn",
-            HashMap::from([
-                ("slot", slot.to_string().as_str()),
-                ("stall_sale_id", stall_sale_id.to_string().as_str()),
-            ]),
-        )
+        self.call(&StallEarnRequest {
+            slot,
+            stall_sale_id,
+        })
         .await
     }
 
     async fn stall_buy(&self, stall_sale_id: i64, seller_farm_id: i64) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/stall/buy",
-            HashMap::from([
-                ("stall_sale_id", stall_sale_id.to_string().as_str()),
-                ("seller_farm_id", seller_farm_id.to_string().as_str()),
-            ]),
-        )
+        self.call(&StallBuyRequest {
+            stall_sale_id,
+            seller_farm_id,
+        })
         .await
     }
 
@@ -91,24 +100,16 @@ impl StallApi for WeLoveClient {
         ad: bool,
         rainbow_coin: i64,
     ) -> WeLoveResult<Response> {
-        self.post(
-            "/v1/game/farm/stall/onshelf",
-            HashMap::from([
-                ("slot", slot.to_string().as_str()),
-                ("item_id", item_id.to_string().as_str()),
-                ("count", count.to_string().as_str()),
-                ("coin", coin.to_string().as_str()),
-                ("ad", (ad as u32).to_string().as_str()),
-                ("rainbow_coin", rainbow_coin.to_string().as_str()),
-                (
-                    "check",
-                    rand::distributions::Alphanumeric
-                        .sample_string(&mut rand::thread_rng(), 6)
-                        .as_str(),
-                ),
-            ]),
-        )
-        .await
+        let req = StallOnshelfRequest {
+            slot,
+            item_id,
+            count,
+            coin,
+            ad,
+            rainbow_coin,
+            check: rand::distributions::Alphanumeric.sample_string(&mut rand::thread_rng(), 6),
+        };
+        self.call(&req).await
     }
 }
 
@@ -0,0 +1,61 @@
+use std::path::Path;
+use std::time::Duration;
+use welove520::catalog::ItemCatalog;
+use welove520::config::Config;
+use welove520::sim::backtest::backtest;
+use welove520::sim::{SimConfig, Simulator};
+
+/// Read when `CONFIG` is not set and the file exists.
+const DEFAULT_CONFIG: &str = "welove520.toml";
+
+/// Runs the configured strategy against the simulator and prints what it earned.
+/// `HOURS` sets the simulated time (default 24) and `SEED` the simulator seed.
+#[tokio::main]
+async fn main() {
+    let mut config = match std::env::var("CONFIG") {
+        Ok(path) => Config::load(path).expect("failed to load config"),
+        Err(_) if Path::new(DEFAULT_CONFIG).exists() => {
+            Config::load(DEFAULT_CONFIG).expect("failed to load config")
+        }
+        Err(_) => Config::default(),
+    };
+    let mut catalog = ItemCatalog::bundled();
+    if let Ok(path) = std::env::var("ITEM_OVERRIDES") {
+        catalog
+            .load_overrides(path)
+            .expect("failed to load item overrides");
+    }
+    // The simulator needs no account.
+    for value in [
+        &mut config.account.base_url,
+        &mut config.account.version,
+        &mut config.account.union_id,
+    ] {
+        if value.is_empty() {
+            *value = "sim".to_string();
+        }
+    }
+    if let Err(err) = config.validate(&catalog) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+    let hours: u64 = std::env::var("HOURS").map_or(24, |s| s.parse().expect("invalid HOURS"));
+    let mut sim_config = SimConfig::default();
+    if let Ok(seed) = std::env::var("SEED") {
+        sim_config.seed = seed.parse().expect("invalid SEED");
+    }
+
+    let sim = Simulator::new(sim_config, catalog.clone());
+    let mut strategy = config.strategy(&catalog);
+    let report = backtest(
+        &mut strategy,
+        &sim,
+        &catalog,
+        &config.timeline(),
+        config.timing.reward_delay(),
+        Duration::from_secs(hours * 3600),
+    )
+    .await
+    .expect("backtest failed");
+    println!("{report}");
+}
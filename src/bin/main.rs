@@ -1,316 +1,345 @@
-use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
 use tracing::Level;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use welove520::api::crops::{CropsApi, Farmland};
+use welove520::api::cassette::Cassette;
 use welove520::api::market::MarketApi;
+use welove520::api::message::Message;
+use welove520::api::middleware::TracingMiddleware;
 use welove520::api::order::OrderApi;
 use welove520::api::panorama::PanoramaApi;
+use welove520::api::rate_limit::RateLimiter;
 use welove520::api::stall::StallApi;
-use welove520::api::WeLoveClient;
+use welove520::api::{Response, WeLoveClient};
+use welove520::bot::dry_run::DryRun;
+use welove520::bot::executor::Executor;
+use welove520::bot::rewards::RewardQueue;
+use welove520::bot::wheat::WHEAT_ITEM_ID;
+use welove520::bot::{Action, FarmState, Strategy};
+use welove520::catalog::ItemCatalog;
+use welove520::config::Config;
+use welove520::error::WeLoveResult;
+use welove520::scheduler::{Job, JobFuture, Scheduler, Trigger};
+use welove520::timeline::Event;
+use welove520::utils::unix_now;
 
-const WHEAT_ITEM_ID: i64 = 201001;
-const BUY_IDS: [i64; 8] = [209001, 209002, 209003, 209004, 210001, 210002, 210003, 210004];
+/// Read when `CONFIG` is not set and the file exists.
+const DEFAULT_CONFIG: &str = "welove520.toml";
 
 #[tokio::main]
 async fn main() {
     init_log();
-    let base_url = std::env::var("BASE_URL").expect("env BASE_URL is not set");
-    let version = std::env::var("VERSION").expect("env VERSION is not set");
-    let union_id = std::env::var("UNION_ID").expect("env UNION_ID is not set");
-    let cli = WeLoveClient::new(
-        base_url,
-        HashMap::from([
-            ("fv".to_string(), version),
-            ("union_id".to_string(), union_id),
-        ]),
-        Default::default(),
-    );
-    let mut i = 0;
-    loop {
-        tracing::info!("loop: {i}");
-        i += 1;
-        harvest_and_plant(&cli).await;
-        let harvest_sleep = tokio::time::sleep(std::time::Duration::from_secs(125));
-        let mut warehouse_items = get_warehouse_items(&cli).await;
-        tracing::info!(
-            "after harvest_and_plant, wheat_count: {}",
-            get_warehouse_item_count(&warehouse_items, WHEAT_ITEM_ID)
-        );
-        stall_renew(&cli, &mut warehouse_items).await;
-        tracing::info!(
-            "after stall_renew, wheat_count: {}",
-            get_warehouse_item_count(&warehouse_items, WHEAT_ITEM_ID)
+    let config_path = std::env::var("CONFIG").ok();
+    let mut config = match &config_path {
+        Some(path) => Config::load(path).expect("failed to load config"),
+        None if Path::new(DEFAULT_CONFIG).exists() => {
+            Config::load(DEFAULT_CONFIG).expect("failed to load config")
+        }
+        None => Config::default(),
+    };
+    config.account_from_env();
+    if std::env::var("DRY_RUN").is_ok() {
+        config.dry_run = true;
+    }
+    if config.dry_run {
+        tracing::warn!("dry run, actions are logged but not sent");
+    }
+    let mut catalog = ItemCatalog::bundled();
+    if let Ok(path) = std::env::var("ITEM_OVERRIDES") {
+        catalog
+            .load_overrides(path)
+            .expect("failed to load item overrides");
+    }
+    if let Err(err) = config.validate(&catalog) {
+        tracing::error!("{err}");
+        std::process::exit(1);
+    }
+    let mut builder = config
+        .client_builder()
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .rate_limiter(
+            RateLimiter::new()
+                .global(2.0, 3)
+                .path("/v1/game/farm/stall/onshelf", 1.0, 1)
+                .path("/v1/game/farm/stall/earn", 1.0, 1)
+                .path("/v1/game/farm/order/refuse", 1.0, 1)
+                .path("/v1/game/farm/market/buy", 1.0, 1),
         );
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-        sale_remain_wheat(&cli, &mut warehouse_items, 10).await;
-        auto_orders(&cli, &mut warehouse_items).await;
-        buy_ingot(&cli).await;
-        harvest_sleep.await;
+    if let Ok(path) = std::env::var("RECORD_CASSETTE") {
+        builder = builder.cassette(Cassette::record(path).expect("failed to create cassette"));
+    } else if let Ok(path) = std::env::var("REPLAY_CASSETTE") {
+        builder = builder.cassette(Cassette::replay(path).expect("failed to load cassette"));
     }
-}
-
-async fn harvest_and_plant(cli: &WeLoveClient) {
-    let fields: Vec<_> = cli.get_fields().await.unwrap_or_default();
-
-    let mut harvest_fields: Vec<_> = fields
-        .iter()
-        .filter(|f| f.plant_item_id == WHEAT_ITEM_ID && f.left_time < 0) // TODO harvest all？
-        .collect();
-    harvest_fields.sort_unstable_by(|a, b| match a.x.cmp(&b.x) {
-        Ordering::Equal => a.y.cmp(&b.y),
-        ord => ord,
+    if std::env::var("TRACE_REQUESTS").is_ok() {
+        builder = builder.middleware(TracingMiddleware);
+    }
+    let cli = builder.build().expect("failed to build client");
+    let rewards = config.reward_queue().expect("failed to load reward queue");
+    let dry_run = config
+        .dry_run
+        .then(|| std::sync::Mutex::new(DryRun::new(config.crops.harvest_yield)));
+    let ctx = Arc::new(Context {
+        cli,
+        strategy: std::sync::Mutex::new(Box::new(config.strategy(&catalog))),
+        catalog,
+        config,
+        warehouse_items: Mutex::new(HashMap::new()),
+        warehouse_changed: Trigger::new(),
+        rewards: std::sync::Mutex::new(rewards),
+        rewards_changed: Trigger::new(),
+        dry_run,
     });
+    let job = |name: &str, f: fn(Arc<Context>) -> JobFuture| {
+        let ctx = ctx.clone();
+        Job::new(name, move || f(ctx.clone()))
+    };
+    Scheduler::new()
+        .job(job("harvest", |ctx| Box::pin(harvest_job(ctx))))
+        .job(
+            job("stall", |ctx| Box::pin(stall_job(ctx)))
+                .triggered_by(&ctx.warehouse_changed)
+                .wait_for_trigger(),
+        )
+        .job(
+            job("orders", |ctx| Box::pin(orders_job(ctx)))
+                .triggered_by(&ctx.warehouse_changed)
+                .wait_for_trigger(),
+        )
+        .job(job("market", |ctx| Box::pin(market_job(ctx))))
+        .job(job("rewards", |ctx| Box::pin(rewards_job(ctx))).triggered_by(&ctx.rewards_changed))
+        .run()
+        .await;
+}
 
-    let mut empty_fields: Vec<_> = fields.iter().filter(|f| f.plant_item_id == -1).collect();
+/// State shared by the jobs. The warehouse counts are locked for a job's whole run,
+/// so two jobs never spend the same items.
+struct Context {
+    cli: WeLoveClient,
+    catalog: ItemCatalog,
+    config: Config,
+    strategy: std::sync::Mutex<Box<dyn Strategy + Send>>,
+    warehouse_items: Mutex<HashMap<i64, i64>>,
+    /// Fired after the harvest job synced the warehouse with the server.
+    warehouse_changed: Trigger,
+    /// Rewards of accomplished orders, claimed by the rewards job.
+    rewards: std::sync::Mutex<RewardQueue>,
+    /// Fired when a reward was queued.
+    rewards_changed: Trigger,
+    /// Actions pretended in a dry run, patched into every fetched state.
+    dry_run: Option<std::sync::Mutex<DryRun>>,
+}
 
-    let harvest_farmland_ids = harvest_fields.iter().map(|f| f.id).collect();
-    tracing::info!("harvest_farmland_ids: {harvest_farmland_ids:?}");
-    if let Err(err) = cli.crops_harvest(201001, harvest_farmland_ids).await {
-        tracing::error!("failed to harvest: {err}");
+impl Context {
+    /// Plans with the strategy and executes the actions.
+    async fn run_strategy(
+        &self,
+        state: FarmState,
+        warehouse_items: &mut HashMap<i64, i64>,
+    ) -> Vec<(Action, WeLoveResult<Response>)> {
+        let actions = self.strategy.lock().unwrap().plan(&state);
+        let outcomes = Executor::new(&self.cli, &self.catalog)
+            .execute(actions, warehouse_items)
+            .await;
+        if let Some(dry_run) = &self.dry_run {
+            let mut dry_run = dry_run.lock().unwrap();
+            for (action, result) in outcomes.iter() {
+                if result.is_ok() {
+                    dry_run.record(action, unix_now(), warehouse_items);
+                }
+            }
+        }
+        outcomes
     }
-    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-    empty_fields.extend(harvest_fields);
-    let mut farmlands: Vec<_> = empty_fields
-        .into_iter()
-        .map(|f| Farmland {
-            id: f.id,
-            last_interval: 1,
-            x: f.x,
-            y: f.y,
-        })
-        .collect();
-    farmlands.sort_unstable_by(|a, b| match a.x.cmp(&b.x) {
-        Ordering::Equal => a.y.cmp(&b.y),
-        ord => ord,
-    });
-    if !farmlands.is_empty() {
-        tracing::info!(
-            "plant_farmlands: {:?}",
-            farmlands.iter().map(|f| f.id).collect::<Vec<_>>()
-        );
-        if let Err(err) = cli.crops_plant(WHEAT_ITEM_ID, farmlands).await {
-            tracing::error!("failed to plant: {err}");
+
+    /// Patches a fetched state with the actions a dry run pretended.
+    fn apply_dry_run(&self, state: &mut FarmState) {
+        if let Some(dry_run) = &self.dry_run {
+            dry_run
+                .lock()
+                .unwrap()
+                .apply(state, &self.catalog, unix_now());
         }
     }
 }
 
-async fn stall_renew(cli: &WeLoveClient, warehouse_items: &mut HashMap<i64, i64>) {
-    let stall = match cli.stall_query().await {
-        Ok(stall) => stall,
+async fn harvest_job(ctx: Arc<Context>) -> Duration {
+    let mut warehouse_items = ctx.warehouse_items.lock().await;
+    let snapshot = match ctx.cli.snapshot().await {
+        Ok(snapshot) => snapshot,
         Err(err) => {
-            tracing::error!("failed to query stall: {err}");
-            return;
+            tracing::error!("failed to get panorama: {err}");
+            return ctx.config.timing.retry();
         }
     };
-    tracing::info!("stall last_free_ad_time: {}", stall.last_free_ad_time);
-    let mut ad = stall.last_free_ad_time == 0;
-    let not_empty_slots: Vec<_> = stall.stall_items.iter().map(|item| item.slot).collect();
-    let empty_slots: Vec<_> = (1..=stall.capacity)
-        .filter(|slot| !not_empty_slots.contains(slot))
-        .collect();
-    tracing::info!("stall empty_slots: {empty_slots:?}");
-    for slot in empty_slots {
-        if get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) >= 10 {
-            if let Err(err) = cli
-                .stall_onshelf(slot, WHEAT_ITEM_ID, 10, 36, std::mem::take(&mut ad), 0)
-                .await
-            {
-                tracing::error!("failed to onshelf: {err}");
-            } else {
-                tracing::info!("succeed to onshelf, slot: {}", slot);
-                warehouse_items.insert(
-                    WHEAT_ITEM_ID,
-                    get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) - 10,
-                );
-            }
-        }
+    let mut state = FarmState::from(snapshot);
+    ctx.apply_dry_run(&mut state);
+    let mut timeline = ctx.config.timeline();
+    if let Some(snapshot) = &state.snapshot {
+        timeline.add_fields(&snapshot.fields, &ctx.catalog, Instant::now());
     }
-
-    for item in stall.stall_items.iter().filter(|item| item.status == 2) {
-        if let Err(err) = cli.stall_earn(item.slot, item.id).await {
-            tracing::error!("failed to earn: {err}");
-        } else if get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) >= 10 {
-            if let Err(err) = cli
-                .stall_onshelf(item.slot, WHEAT_ITEM_ID, 10, 36, std::mem::take(&mut ad), 0)
-                .await
-            {
-                tracing::error!("failed to onshelf: {err}");
-            } else {
-                tracing::info!("succeed to onshelf after earn, slot: {}", item.slot);
-                warehouse_items.insert(
-                    WHEAT_ITEM_ID,
-                    get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) - 10,
-                );
-            }
-        } else {
-            tracing::info!(
-                "succeed to earn, slot: {}, wheat is not enough: {}",
-                item.slot,
-                get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID)
-            );
+    *warehouse_items = state.warehouse_items.clone();
+    for (action, result) in ctx.run_strategy(state, &mut warehouse_items).await {
+        if let (Action::Plant { item_id, farmlands }, Ok(_)) = (action, result) {
+            let field_ids: Vec<_> = farmlands.iter().map(|f| f.id).collect();
+            timeline.add_planted(&field_ids, item_id, &ctx.catalog, Instant::now());
         }
     }
+    tracing::info!(
+        "after harvest_and_plant, wheat_count: {}",
+        get_warehouse_item_count(&warehouse_items, WHEAT_ITEM_ID)
+    );
+    drop(warehouse_items);
+    ctx.warehouse_changed.fire();
+    timeline.next_delay(Instant::now())
 }
 
-async fn get_warehouse_items(cli: &WeLoveClient) -> HashMap<i64, i64> {
-    cli.get_warehouses()
-        .await
-        .unwrap_or_default()
-        .into_iter()
-        .flat_map(|w| w.items)
-        .map(|item| (item.item_id, item.count))
-        .collect()
-}
-
-fn get_warehouse_item_count(warehouse_items: &HashMap<i64, i64>, item_id: i64) -> i64 {
-    warehouse_items.get(&item_id).copied().unwrap_or_default()
-}
-
-async fn sale_remain_wheat(
-    cli: &WeLoveClient,
-    warehouse_items: &mut HashMap<i64, i64>,
-    remain: i64,
-) {
-    if get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) > remain {
-        if let Err(err) = cli
-            .market_sale(
-                WHEAT_ITEM_ID,
-                get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) - remain,
-            )
-            .await
-        {
-            tracing::error!("failed to sale wheat: {err}")
-        } else {
-            tracing::info!(
-                "after sale remain wheat, count: {}, remain: 10",
-                get_warehouse_item_count(warehouse_items, WHEAT_ITEM_ID) - remain
-            );
-            warehouse_items.insert(WHEAT_ITEM_ID, 10);
+async fn stall_job(ctx: Arc<Context>) -> Duration {
+    let mut warehouse_items = ctx.warehouse_items.lock().await;
+    let stall = match ctx.cli.stall_query().await {
+        Ok(stall) => stall,
+        Err(err) => {
+            tracing::error!("failed to query stall: {err}");
+            return ctx.config.timing.retry();
+        }
+    };
+    let mut state = FarmState {
+        warehouse_items: warehouse_items.clone(),
+        stall: Some(stall),
+        ..Default::default()
+    };
+    ctx.apply_dry_run(&mut state);
+    let stall = state.stall.as_ref().unwrap();
+    tracing::info!("stall last_free_ad_time: {}", stall.last_free_ad_time);
+    let mut timeline = ctx.config.timeline();
+    timeline.add_stall(stall, Instant::now());
+    for (action, result) in ctx.run_strategy(state, &mut warehouse_items).await {
+        if let (Action::Onshelf { .. }, Ok(_)) = (action, result) {
+            timeline.push(Instant::now() + timeline.stall_poll, Event::StallPoll);
         }
     }
+    tracing::info!(
+        "after stall_renew, wheat_count: {}",
+        get_warehouse_item_count(&warehouse_items, WHEAT_ITEM_ID)
+    );
+    timeline.next_delay(Instant::now())
 }
 
-async fn auto_orders(cli: &WeLoveClient, warehouse_items: &mut HashMap<i64, i64>) {
-    let order_info = match cli.order_query().await {
-        Ok(order_info) => order_info,
+async fn orders_job(ctx: Arc<Context>) -> Duration {
+    let mut warehouse_items = ctx.warehouse_items.lock().await;
+    let mut state = match ctx.cli.order_query().await {
+        Ok(order_info) => FarmState {
+            warehouse_items: warehouse_items.clone(),
+            orders: Some(order_info),
+            ..Default::default()
+        },
         Err(err) => {
             tracing::error!("failed to query order: {err}");
-            return;
+            return ctx.config.timing.retry();
         }
     };
-    let (orders, mut waiting_slots): (Vec<_>, Vec<_>) = order_info
+    ctx.apply_dry_run(&mut state);
+    let order_info = state.orders.as_mut().unwrap();
+    let mut timeline = ctx.config.timeline();
+    timeline.add_orders(&order_info.orders, Instant::now());
+    let mut waiting_slots: Vec<_> = order_info
         .orders
-        .into_iter()
-        .partition(|o| o.time_left <= 0);
-
+        .iter()
+        .filter(|o| o.time_left > 0)
+        .collect();
     if !waiting_slots.is_empty() {
         waiting_slots.sort_unstable_by_key(|s| s.time_left);
         tracing::info!(
             "waiting order time: {:?}",
             waiting_slots
                 .iter()
-                .map(|s| std::time::Duration::from_secs(s.time_left as u64))
+                .map(|s| Duration::from_secs(s.time_left as u64))
                 .collect::<Vec<_>>()
         );
     }
-    if !orders.is_empty() {
-        tracing::info!("ready order count: {}", orders.len());
-    }
-
-    for mut order in orders.into_iter() {
-        loop {
-            let order_item_count: i64 = order.items.iter().map(|item| item.count).sum();
-            if !order
-                .items
-                .iter()
-                .all(|item| get_warehouse_item_count(warehouse_items, item.item_id) >= item.count)
-                || order_item_count > 2
-            {
-                if order.voucher_item_id == 0 || order_item_count > 2 {
-                    if let Err(err) = cli.order_refuse(order.order_id).await {
-                        tracing::error!("failed to refuse order: {err}")
-                    } else {
-                        tracing::info!(
-                            "succeed to refuse order, special: {}, slot: {}, order_id: {}, items: {}",
-                            order.special,
-                            order.slot,
-                            order.order_id,
-                            serde_json::to_string(&order.items).unwrap()
-                        )
-                    }
-                } else {
-                    tracing::info!(
-                        "special order, item is not enough, slot: {} ,order_id: {}",
-                        order.slot,
-                        order.order_id
-                    );
-                }
-                break;
+    // Finished orders are claimed by the rewards job, not the strategy.
+    let mut queued = ctx
+        .rewards
+        .lock()
+        .unwrap()
+        .queue_finished(&mut order_info.orders, unix_now());
+    for (action, result) in ctx.run_strategy(state, &mut warehouse_items).await {
+        match (action, result) {
+            (Action::Accomplish { order_id, .. }, Ok(_)) => {
+                let due = unix_now() + ctx.config.timing.reward_delay().as_secs() as i64;
+                ctx.rewards.lock().unwrap().push(order_id, due);
+                queued = true;
             }
-            let mut accomplish_resp = match cli.order_accomplish(order.order_id, false).await {
-                Ok(resp) => resp,
-                Err(err) => {
-                    tracing::error!("failed to accomplish order: {err}");
-                    break;
+            (Action::Refuse { .. }, Ok(resp)) => {
+                for m in resp.parse_messages() {
+                    if let Message::Orders(order_info) = m {
+                        timeline.add_orders(&order_info.orders, Instant::now());
+                    }
                 }
-            };
-            tracing::info!(
-                "succeed to accomplish order, special: {}, slot: {}, order_id: {}, order: {}",
-                order.special,
-                order.slot,
-                order.order_id,
-                serde_json::to_string(&order).unwrap()
-            );
-            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-            if let Err(err) = cli.order_reward(order.order_id).await {
-                tracing::error!("failed to reward order: {err}");
-            }
-            for item in order.items.iter() {
-                warehouse_items.insert(
-                    item.item_id,
-                    get_warehouse_item_count(warehouse_items, item.item_id) - item.count,
-                );
             }
-            if let Some(msg) = accomplish_resp
-                .messages
-                .iter_mut()
-                .find(|m| m["msg_type"] == 47)
-            {
-                order = serde_json::from_value(msg.take()).unwrap();
-            } else {
-                tracing::error!(
-                    "error accomplish msg: {}",
-                    serde_json::to_string(&accomplish_resp).unwrap()
-                );
+            _ => {}
+        }
+    }
+    if queued {
+        ctx.rewards_changed.fire();
+    }
+    timeline.next_delay(Instant::now())
+}
+
+/// Claims the rewards that are due and sleeps until the next one.
+async fn rewards_job(ctx: Arc<Context>) -> Duration {
+    let due = ctx.rewards.lock().unwrap().due(unix_now());
+    for order_id in due {
+        match ctx.cli.order_reward(order_id).await {
+            Ok(_) => {
+                tracing::info!("succeed to claim reward of order {order_id}");
+                ctx.rewards.lock().unwrap().claimed(order_id);
             }
+            Err(err) => ctx
+                .rewards
+                .lock()
+                .unwrap()
+                .failed(order_id, &err, unix_now()),
         }
     }
+    let timeline = ctx.config.timeline();
+    match ctx.rewards.lock().unwrap().next_due() {
+        Some(due) => Duration::from_secs((due - unix_now()).max(0) as u64)
+            .clamp(timeline.min_sleep, timeline.max_sleep),
+        None => timeline.max_sleep,
+    }
 }
-async fn buy_ingot(cli: &WeLoveClient) {
-    let market_info = match cli.market_query().await {
+
+async fn market_job(ctx: Arc<Context>) -> Duration {
+    let market_info = match ctx.cli.market_query().await {
         Ok(market_info) => market_info,
         Err(err) => {
             tracing::error!("failed to markket_query: {err}");
-            return;
+            return ctx.config.timing.retry();
         }
     };
-    for item in market_info
-        .market_item_list
-        .iter()
-        .filter(|item| BUY_IDS.contains(&item.item_id) && item.sold_out == 0)
-    {
-        if let Err(err) = cli.market_buy(item.id).await {
-            tracing::error!("failed to market_buy: {err}")
-        } else {
-            tracing::info!(
-                "succeed to market_buy, item_id: {}, count: {}",
-                item.item_id,
-                item.count
-            )
-        }
+    let mut warehouse_items = ctx.warehouse_items.lock().await;
+    let mut state = FarmState {
+        warehouse_items: warehouse_items.clone(),
+        market: Some(market_info),
+        ..Default::default()
+    };
+    ctx.apply_dry_run(&mut state);
+    let mut timeline = ctx.config.timeline();
+    if let Some(market_info) = &state.market {
+        timeline.add_market(market_info, Instant::now());
     }
+    ctx.run_strategy(state, &mut warehouse_items).await;
+    timeline.next_delay(Instant::now())
+}
+
+fn get_warehouse_item_count(warehouse_items: &HashMap<i64, i64>, item_id: i64) -> i64 {
+    warehouse_items.get(&item_id).copied().unwrap_or_default()
 }
 
 fn init_log() {
@@ -319,7 +348,7 @@ fn init_log() {
             tracing_subscriber::fmt::layer()
                 .with_target(true)
                 .with_timer(tracing_subscriber::fmt::time::OffsetTime::new(
-                    time::UtcOffset::__from_hms_unchecked(8, 0, 0),
+                    time::macros::offset!(+8),
                     time::macros::format_description!(
                         "[year]-[month]-[day] [hour]:[minute]:[second]"
                     ),
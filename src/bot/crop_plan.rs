@@ -0,0 +1,113 @@
+use crate::api::panorama::Field;
+use std::collections::{BTreeMap, HashMap};
+
+/// Which crop goes into each free field: fixed per-field choices first,
+/// then the remaining fields split by weight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CropPlan {
+    /// Crop item id by field id.
+    pub fields: HashMap<i64, i64>,
+    /// `(item_id, weight)` pairs.
+    pub weights: Vec<(i64, u32)>,
+}
+
+impl CropPlan {
+    /// Plants only `item_id`.
+    pub fn single(item_id: i64) -> Self {
+        Self {
+            fields: HashMap::new(),
+            weights: vec![(item_id, 1)],
+        }
+    }
+
+    /// Parses `item_id:weight,...`, e.g. `201001:3,201002:1`.
+    pub fn parse_weights(s: &str) -> Option<Self> {
+        let weights = s
+            .split(',')
+            .map(|part| {
+                let (item_id, weight) = part.trim().split_once(':')?;
+                Some((item_id.parse().ok()?, weight.parse().ok()?))
+            })
+            .collect::<Option<Vec<(i64, u32)>>>()?;
+        Some(Self {
+            fields: HashMap::new(),
+            weights,
+        })
+    }
+
+    /// Groups `fields` by the crop to plant, keeping their order.
+    pub fn assign<'a>(&self, fields: &[&'a Field]) -> BTreeMap<i64, Vec<&'a Field>> {
+        let mut plan: BTreeMap<i64, Vec<&Field>> = BTreeMap::new();
+        let mut rest = Vec::new();
+        for &f in fields {
+            match self.fields.get(&f.id) {
+                Some(&item_id) => plan.entry(item_id).or_default().push(f),
+                None => rest.push(f),
+            }
+        }
+        let total_weight: u32 = self.weights.iter().map(|(_, w)| w).sum();
+        if total_weight == 0 {
+            return plan;
+        }
+        // largest remainder, so the counts add up to rest.len()
+        let mut counts: Vec<_> = self
+            .weights
+            .iter()
+            .map(|&(item_id, w)| {
+                let share = rest.len() as u64 * w as u64;
+                (
+                    item_id,
+                    share / total_weight as u64,
+                    share % total_weight as u64,
+                )
+            })
+            .collect();
+        let assigned: u64 = counts.iter().map(|(_, n, _)| n).sum();
+        let mut by_remainder: Vec<_> = (0..counts.len()).collect();
+        by_remainder.sort_by_key(|&i| std::cmp::Reverse(counts[i].2));
+        for &i in by_remainder.iter().take(rest.len() - assigned as usize) {
+            counts[i].1 += 1;
+        }
+        let mut rest = rest.into_iter();
+        for (item_id, n, _) in counts {
+            plan.entry(item_id)
+                .or_default()
+                .extend(rest.by_ref().take(n as usize));
+        }
+        plan
+    }
+}
+
+impl Default for CropPlan {
+    fn default() -> Self {
+        Self::single(201001)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign() {
+        let fields: Vec<_> = (1..=7)
+            .map(|id| Field {
+                id,
+                ..Default::default()
+            })
+            .collect();
+        let refs: Vec<_> = fields.iter().collect();
+        let mut plan = CropPlan::parse_weights("201001:2, 201002:1").unwrap();
+        plan.fields.insert(7, 201003);
+        let ids = |item_id| -> Vec<i64> {
+            plan.assign(&refs)
+                .get(&item_id)
+                .map(|fields| fields.iter().map(|f| f.id).collect())
+                .unwrap_or_default()
+        };
+        assert_eq!(ids(201001), [1, 2, 3, 4]);
+        assert_eq!(ids(201002), [5, 6]);
+        assert_eq!(ids(201003), [7]);
+        assert!(CropPlan::parse_weights("201001").is_none());
+    }
+}
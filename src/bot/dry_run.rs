@@ -0,0 +1,227 @@
+use super::{Action, FarmState};
+use crate::api::market::MarketInfo;
+use crate::api::order::OrderInfo;
+use crate::api::panorama::Field;
+use crate::api::stall::{StallInfo, StallItem};
+use crate::catalog::ItemCatalog;
+use std::collections::{HashMap, HashSet};
+
+/// What a dry run pretended to do.
+///
+/// The server never sees these actions, so every fetched state still shows the farm as
+/// it was. Each state is patched with the recorded actions before planning, otherwise
+/// the same harvest, listing or order would be planned again every cycle.
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    /// Units one pretended harvest adds per field.
+    pub harvest_yield: i64,
+    /// Crop and plant time of the fields changed here, `-1` for an emptied field.
+    fields: HashMap<i64, (i64, i64)>,
+    /// Item count changes since the dry run started.
+    items: HashMap<i64, i64>,
+    /// Listings by slot, `None` for an earned slot.
+    listings: HashMap<i64, Option<StallItem>>,
+    last_free_ad_time: i64,
+    /// Orders accomplished or refused.
+    orders: HashSet<i64>,
+    /// Market ids bought.
+    bought: HashSet<i64>,
+}
+
+impl DryRun {
+    pub fn new(harvest_yield: i64) -> Self {
+        Self {
+            harvest_yield,
+            fields: HashMap::new(),
+            items: HashMap::new(),
+            listings: HashMap::new(),
+            last_free_ad_time: 0,
+            orders: HashSet::new(),
+            bought: HashSet::new(),
+        }
+    }
+
+    /// Records an action that succeeded at unix time `now`.
+    ///
+    /// The executor already counted the items an action spends or buys in
+    /// `warehouse_items`; the yield of a harvest is only known here and is added to them.
+    pub fn record(&mut self, action: &Action, now: i64, warehouse_items: &mut HashMap<i64, i64>) {
+        match action {
+            Action::Harvest { item_id, field_ids } => {
+                for id in field_ids.iter() {
+                    self.fields.insert(*id, (-1, 0));
+                }
+                let count = self.harvest_yield * field_ids.len() as i64;
+                self.add(*item_id, count);
+                *warehouse_items.entry(*item_id).or_default() += count;
+            }
+            Action::Plant { item_id, farmlands } => {
+                for farmland in farmlands.iter() {
+                    self.fields.insert(farmland.id, (*item_id, now));
+                }
+            }
+            Action::Onshelf {
+                slot,
+                item_id,
+                count,
+                coin,
+                ad,
+            } => {
+                self.add(*item_id, -count);
+                if *ad {
+                    self.last_free_ad_time = now;
+                }
+                self.listings.insert(
+                    *slot,
+                    Some(StallItem {
+                        item_id: *item_id,
+                        count: *count,
+                        slot: *slot,
+                        coin: *coin,
+                        status: 1,
+                        last_ad_time: if *ad { now } else { 0 },
+                        ..Default::default()
+                    }),
+                );
+            }
+            Action::Earn { slot, .. } => {
+                self.listings.insert(*slot, None);
+            }
+            Action::Sell { item_id, count } => self.add(*item_id, -count),
+            Action::Accomplish { order_id, items } => {
+                for (item_id, count) in items.iter() {
+                    self.add(*item_id, -count);
+                }
+                self.orders.insert(*order_id);
+            }
+            Action::Refuse { order_id } => {
+                self.orders.insert(*order_id);
+            }
+            Action::Buy { id, item_id, count } => {
+                self.add(*item_id, *count);
+                self.bought.insert(*id);
+            }
+        }
+    }
+
+    /// Patches every part of `state`. The warehouse counts are only patched with a
+    /// snapshot, the other parts are planned with counts the executor keeps up to date.
+    pub fn apply(&self, state: &mut FarmState, catalog: &ItemCatalog, now: i64) {
+        if let Some(snapshot) = &mut state.snapshot {
+            self.apply_fields(&mut snapshot.fields, catalog, now);
+            self.apply_items(&mut state.warehouse_items);
+        }
+        if let Some(stall) = &mut state.stall {
+            self.apply_stall(stall);
+        }
+        if let Some(orders) = &mut state.orders {
+            self.apply_orders(orders);
+        }
+        if let Some(market) = &mut state.market {
+            self.apply_market(market);
+        }
+    }
+
+    pub fn apply_fields(&self, fields: &mut [Field], catalog: &ItemCatalog, now: i64) {
+        for field in fields.iter_mut() {
+            let Some(&(item_id, plant_time)) = self.fields.get(&field.id) else {
+                continue;
+            };
+            field.plant_item_id = item_id;
+            field.plant_time = plant_time;
+            field.left_time = match catalog.growth_time(item_id) {
+                Some(growth) => plant_time + growth.as_secs() as i64 - now,
+                None => 0,
+            };
+        }
+    }
+
+    pub fn apply_items(&self, warehouse_items: &mut HashMap<i64, i64>) {
+        for (item_id, count) in self.items.iter() {
+            *warehouse_items.entry(*item_id).or_default() += count;
+        }
+    }
+
+    pub fn apply_stall(&self, stall: &mut StallInfo) {
+        stall
+            .stall_items
+            .retain(|item| !self.listings.contains_key(&item.slot));
+        stall
+            .stall_items
+            .extend(self.listings.values().flatten().cloned());
+        stall.stall_items.sort_unstable_by_key(|item| item.slot);
+        stall.last_free_ad_time = stall.last_free_ad_time.max(self.last_free_ad_time);
+    }
+
+    pub fn apply_orders(&self, order_info: &mut OrderInfo) {
+        order_info
+            .orders
+            .retain(|order| !self.orders.contains(&order.order_id));
+    }
+
+    pub fn apply_market(&self, market: &mut MarketInfo) {
+        for item in market.market_item_list.iter_mut() {
+            if self.bought.contains(&item.id) {
+                item.sold_out = 1;
+            }
+        }
+    }
+
+    fn add(&mut self, item_id: i64, count: i64) {
+        *self.items.entry(item_id).or_default() += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::panorama::PanoramaApi;
+    use crate::api::stall::StallApi;
+    use crate::api::tests::get_mock_client;
+    use crate::bot::executor::Executor;
+    use crate::bot::wheat::WheatStrategy;
+    use crate::bot::Strategy;
+    use crate::catalog::tests::test_catalog;
+
+    #[tokio::test]
+    async fn test_dry_run_state() {
+        let (mut cli, _farm) = get_mock_client();
+        cli.dry_run = true;
+        let catalog = test_catalog();
+        let mut strategy = WheatStrategy::default();
+        let mut dry_run = DryRun::new(2);
+        let now = 1000;
+
+        let mut state = FarmState::from(cli.snapshot().await.unwrap());
+        state.stall = Some(cli.stall_query().await.unwrap());
+        let before = state.clone();
+        let mut warehouse_items = state.warehouse_items.clone();
+        let actions = strategy.plan(&state);
+        assert!(actions.iter().any(|a| matches!(a, Action::Harvest { .. })));
+        for (action, result) in Executor::new(&cli, &catalog)
+            .execute(actions, &mut warehouse_items)
+            .await
+        {
+            if result.is_ok() {
+                dry_run.record(&action, now, &mut warehouse_items);
+            }
+        }
+
+        let mut state = before;
+        dry_run.apply(&mut state, &catalog, now);
+        assert_eq!(state.warehouse_items, warehouse_items);
+        assert!(state
+            .snapshot
+            .as_ref()
+            .unwrap()
+            .fields
+            .iter()
+            .all(|f| { f.plant_item_id != -1 && f.left_time > 0 }));
+        let stall = state.stall.as_ref().unwrap();
+        assert!(stall.stall_items.iter().all(|item| item.status != 2));
+        assert!(!strategy.plan(&state).iter().any(|a| matches!(
+            a,
+            Action::Harvest { .. } | Action::Plant { .. } | Action::Earn { .. }
+        )));
+    }
+}
@@ -0,0 +1,272 @@
+use super::Action;
+use crate::api::crops::CropsApi;
+use crate::api::market::MarketApi;
+use crate::api::message::Message;
+use crate::api::order::OrderApi;
+use crate::api::panorama::Warehouse;
+use crate::api::stall::StallApi;
+use crate::api::Response;
+use crate::catalog::ItemCatalog;
+use crate::error::{WeLoveError, WeLoveResult};
+use std::collections::{HashMap, HashSet};
+
+/// Carries out [`Action`]s and keeps the warehouse counts up to date.
+///
+/// Actions that depend on a failed one are skipped: fields whose harvest failed are not
+/// planted, a slot whose earn failed is not put on shelf, and items are never spent
+/// beyond the warehouse counts.
+pub struct Executor<'a, C> {
+    cli: &'a C,
+    catalog: &'a ItemCatalog,
+}
+
+impl<'a, C> Executor<'a, C>
+where
+    C: CropsApi + StallApi + MarketApi + OrderApi,
+{
+    pub fn new(cli: &'a C, catalog: &'a ItemCatalog) -> Self {
+        Self { cli, catalog }
+    }
+
+    /// Executes the actions in order and returns each one with its result.
+    pub async fn execute(
+        &self,
+        actions: Vec<Action>,
+        warehouse_items: &mut HashMap<i64, i64>,
+    ) -> Vec<(Action, WeLoveResult<Response>)> {
+        let mut failed_fields: HashSet<i64> = HashSet::new();
+        let mut failed_slots = HashSet::new();
+        let mut outcomes = Vec::with_capacity(actions.len());
+        for mut action in actions {
+            match &mut action {
+                Action::Plant { farmlands, .. } => {
+                    farmlands.retain(|f| !failed_fields.contains(&f.id));
+                    if farmlands.is_empty() {
+                        continue;
+                    }
+                }
+                Action::Onshelf { slot, .. } if failed_slots.contains(slot) => continue,
+                _ => {}
+            }
+            let result = self.execute_one(&action, warehouse_items).await;
+            match &result {
+                Ok(_) => tracing::info!("succeed to {}", self.describe(&action)),
+                Err(err) => {
+                    tracing::error!("failed to {}: {err}", self.describe(&action));
+                    match &action {
+                        Action::Harvest { field_ids, .. } => failed_fields.extend(field_ids),
+                        Action::Earn { slot, .. } => {
+                            failed_slots.insert(*slot);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            outcomes.push((action, result));
+        }
+        outcomes
+    }
+
+    async fn execute_one(
+        &self,
+        action: &Action,
+        warehouse_items: &mut HashMap<i64, i64>,
+    ) -> WeLoveResult<Response> {
+        let cli = self.cli;
+        let resp = match action {
+            Action::Harvest { item_id, field_ids } => {
+                cli.crops_harvest(*item_id, field_ids.clone()).await?
+            }
+            Action::Plant { item_id, farmlands } => {
+                cli.crops_plant(*item_id, farmlands.clone()).await?
+            }
+            Action::Onshelf {
+                slot,
+                item_id,
+                count,
+                coin,
+                ad,
+            } => {
+                take_items(warehouse_items, &[(*item_id, *count)])?;
+                cli.stall_onshelf(*slot, *item_id, *count, *coin, *ad, 0)
+                    .await
+                    .inspect_err(|_| put_items(warehouse_items, &[(*item_id, *count)]))?
+            }
+            Action::Earn {
+                slot,
+                stall_sale_id,
+            } => cli.stall_earn(*slot, *stall_sale_id).await?,
+            Action::Sell { item_id, count } => {
+                take_items(warehouse_items, &[(*item_id, *count)])?;
+                cli.market_sale(*item_id, *count)
+                    .await
+                    .inspect_err(|_| put_items(warehouse_items, &[(*item_id, *count)]))?
+            }
+            Action::Accomplish { order_id, items } => {
+                take_items(warehouse_items, items)?;
+                cli.order_accomplish(*order_id, false)
+                    .await
+                    .inspect_err(|_| put_items(warehouse_items, items))?
+            }
+            Action::Refuse { order_id } => cli.order_refuse(*order_id).await?,
+            Action::Buy { id, .. } => cli.market_buy(*id).await?,
+        };
+        let mut synced = false;
+        for m in resp.parse_messages() {
+            if let Message::Warehouses(warehouses) = m {
+                *warehouse_items = Warehouse::item_counts(&warehouses);
+                synced = true;
+            }
+        }
+        // a dry run answers without messages, so count the bought items here;
+        // harvest yields are unknown until the next snapshot
+        if let (false, Action::Buy { item_id, count, .. }) = (synced, action) {
+            put_items(warehouse_items, &[(*item_id, *count)]);
+        }
+        Ok(resp)
+    }
+
+    fn describe(&self, action: &Action) -> String {
+        let name = |item_id| self.catalog.name(item_id);
+        match action {
+            Action::Harvest { item_id, field_ids } => {
+                format!("harvest {}, farmland_ids: {field_ids:?}", name(*item_id))
+            }
+            Action::Plant { item_id, farmlands } => format!(
+                "plant {}, farmlands: {:?}",
+                name(*item_id),
+                farmlands.iter().map(|f| f.id).collect::<Vec<_>>()
+            ),
+            Action::Onshelf {
+                slot,
+                item_id,
+                count,
+                coin,
+                ad,
+            } => format!(
+                "onshelf {} x{count} for {coin} coins, slot: {slot}, ad: {ad}",
+                name(*item_id)
+            ),
+            Action::Earn { slot, .. } => format!("earn, slot: {slot}"),
+            Action::Sell { item_id, count } => format!("sell {} x{count}", name(*item_id)),
+            Action::Accomplish { order_id, items } => format!(
+                "accomplish order {order_id}, items: {:?}",
+                items
+                    .iter()
+                    .map(|&(item_id, count)| format!("{} x{count}", name(item_id)))
+                    .collect::<Vec<_>>()
+            ),
+            Action::Refuse { order_id } => format!("refuse order {order_id}"),
+            Action::Buy { id, item_id, count } => {
+                format!("market_buy {} x{count}, id: {id}", name(*item_id))
+            }
+        }
+    }
+}
+
+fn take_items(warehouse_items: &mut HashMap<i64, i64>, items: &[(i64, i64)]) -> WeLoveResult<()> {
+    if let Some((item_id, _)) = items
+        .iter()
+        .find(|(item_id, count)| warehouse_items.get(item_id).copied().unwrap_or_default() < *count)
+    {
+        return Err(WeLoveError::NotEnoughItems(format!(
+            "item {item_id} is not enough"
+        )));
+    }
+    for (item_id, count) in items {
+        *warehouse_items.entry(*item_id).or_default() -= count;
+    }
+    Ok(())
+}
+
+fn put_items(warehouse_items: &mut HashMap<i64, i64>, items: &[(i64, i64)]) {
+    for (item_id, count) in items {
+        *warehouse_items.entry(*item_id).or_default() += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::crops::Farmland;
+    use crate::api::tests::get_mock_client;
+    use crate::catalog::tests::test_catalog;
+
+    #[tokio::test]
+    async fn test_execute() {
+        let (cli, farm) = get_mock_client();
+        let catalog = test_catalog();
+        let mut warehouse_items = farm.lock().unwrap().warehouse.clone();
+        let farmland = |id| Farmland {
+            id,
+            last_interval: 1,
+            ..Default::default()
+        };
+        let outcomes = Executor::new(&cli, &catalog)
+            .execute(
+                vec![
+                    Action::Harvest {
+                        item_id: 201001,
+                        field_ids: vec![3],
+                    },
+                    Action::Plant {
+                        item_id: 201001,
+                        farmlands: vec![farmland(3), farmland(5)],
+                    },
+                    Action::Sell {
+                        item_id: 201002,
+                        count: 10,
+                    },
+                    Action::Sell {
+                        item_id: 201001,
+                        count: 10,
+                    },
+                ],
+                &mut warehouse_items,
+            )
+            .await;
+        assert!(outcomes[0].1.is_err());
+        assert_eq!(
+            outcomes[1].0,
+            Action::Plant {
+                item_id: 201001,
+                farmlands: vec![farmland(5)],
+            }
+        );
+        assert!(outcomes[1].1.is_ok());
+        assert!(matches!(outcomes[2].1, Err(WeLoveError::NotEnoughItems(_))));
+        assert!(outcomes[3].1.is_ok());
+        assert_eq!(warehouse_items, farm.lock().unwrap().warehouse);
+        assert_eq!(warehouse_items[&201001], 20);
+    }
+
+    #[tokio::test]
+    async fn test_execute_dry_run() {
+        let (mut cli, farm) = get_mock_client();
+        cli.dry_run = true;
+        let catalog = test_catalog();
+        let mut warehouse_items = farm.lock().unwrap().warehouse.clone();
+        let outcomes = Executor::new(&cli, &catalog)
+            .execute(
+                vec![
+                    Action::Sell {
+                        item_id: 201001,
+                        count: 10,
+                    },
+                    Action::Buy {
+                        id: 1,
+                        item_id: 209001,
+                        count: 1,
+                    },
+                ],
+                &mut warehouse_items,
+            )
+            .await;
+        assert!(outcomes.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(warehouse_items[&201001], 20);
+        assert_eq!(warehouse_items[&209001], 3);
+        let farm = farm.lock().unwrap();
+        assert_eq!(farm.warehouse[&201001], 30);
+        assert_eq!(farm.warehouse[&209001], 2);
+    }
+}
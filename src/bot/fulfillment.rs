@@ -0,0 +1,127 @@
+use crate::api::order::Order;
+use crate::api::panorama::Field;
+use std::collections::{BTreeMap, HashMap};
+
+/// Stock held back and crops grown for orders that wait for their items.
+///
+/// Rebuilt whenever orders are planned and kept in between, so that stall and market
+/// plans made without the orders still leave the reserved stock alone.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Fulfillment {
+    /// Warehouse units held back for waiting orders, by item id.
+    pub reserved: HashMap<i64, i64>,
+    /// Units the warehouse is short of for waiting orders, by item id.
+    pub shortfall: BTreeMap<i64, i64>,
+    /// Waiting orders, in the order they were added.
+    pub order_ids: Vec<i64>,
+}
+
+impl Fulfillment {
+    /// Reserves what `items` holds for `order` and records the rest as shortfall.
+    /// Reserved units are taken out of `items`.
+    pub fn add_order(&mut self, order: &Order, items: &mut HashMap<i64, i64>) {
+        for item in order.items.iter() {
+            let stock = items.entry(item.item_id).or_default();
+            let take = item.count.min(*stock).max(0);
+            *stock -= take;
+            if take > 0 {
+                *self.reserved.entry(item.item_id).or_default() += take;
+            }
+            if item.count > take {
+                *self.shortfall.entry(item.item_id).or_default() += item.count - take;
+            }
+        }
+        self.order_ids.push(order.order_id);
+    }
+
+    /// Takes the reserved units out of `items`, and the shortfall too since
+    /// harvested units are already spoken for.
+    pub fn reserve(&self, items: &mut HashMap<i64, i64>) {
+        for (item_id, count) in self.reserved.iter().chain(self.shortfall.iter()) {
+            if let Some(stock) = items.get_mut(item_id) {
+                *stock = (*stock - count).max(0);
+            }
+        }
+    }
+
+    /// How many of `free` fields to plant with each crop to cover the shortfall.
+    /// Crops already in `fields`, ready or not, count as covered.
+    pub fn crops_to_plant(
+        &self,
+        fields: &[Field],
+        harvest_yield: i64,
+        mut free: usize,
+    ) -> BTreeMap<i64, usize> {
+        let harvest_yield = harvest_yield.max(1);
+        let mut plan = BTreeMap::new();
+        for (&item_id, &short) in self.shortfall.iter() {
+            let growing =
+                fields.iter().filter(|f| f.plant_item_id == item_id).count() as i64 * harvest_yield;
+            let needed = (short - growing).max(0);
+            let count = (((needed + harvest_yield - 1) / harvest_yield) as usize).min(free);
+            if count > 0 {
+                plan.insert(item_id, count);
+                free -= count;
+            }
+        }
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::order::OrderItem;
+
+    #[test]
+    fn test_fulfillment() {
+        let order = Order {
+            order_id: 1,
+            items: vec![
+                OrderItem {
+                    item_id: 201001,
+                    count: 4,
+                },
+                OrderItem {
+                    item_id: 201002,
+                    count: 5,
+                },
+            ],
+            ..Default::default()
+        };
+        let mut items = HashMap::from([(201001, 10), (201002, 1)]);
+        let mut fulfillment = Fulfillment::default();
+        fulfillment.add_order(&order, &mut items);
+        assert_eq!(items, HashMap::from([(201001, 6), (201002, 0)]));
+        assert_eq!(
+            fulfillment.reserved,
+            HashMap::from([(201001, 4), (201002, 1)])
+        );
+        assert_eq!(fulfillment.shortfall, BTreeMap::from([(201002, 4)]));
+
+        let mut items = HashMap::from([(201001, 5), (201002, 6)]);
+        fulfillment.reserve(&mut items);
+        assert_eq!(items, HashMap::from([(201001, 1), (201002, 1)]));
+
+        let corn = Field {
+            id: 1,
+            plant_item_id: 201002,
+            ..Default::default()
+        };
+        assert_eq!(
+            fulfillment.crops_to_plant(&[], 2, 5),
+            BTreeMap::from([(201002, 2)])
+        );
+        assert_eq!(
+            fulfillment.crops_to_plant(std::slice::from_ref(&corn), 2, 5),
+            BTreeMap::from([(201002, 1)])
+        );
+        assert!(fulfillment
+            .crops_to_plant(&[corn.clone(), corn], 2, 5)
+            .is_empty());
+        assert_eq!(
+            fulfillment.crops_to_plant(&[], 2, 1),
+            BTreeMap::from([(201002, 1)])
+        );
+    }
+}
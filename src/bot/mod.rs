@@ -0,0 +1,98 @@
+//! Farm automation: a [`Strategy`] decides what to do from the farm state, and an
+//! [`Executor`](executor::Executor) carries the actions out through the API traits.
+pub mod crop_plan;
+pub mod dry_run;
+pub mod executor;
+pub mod fulfillment;
+pub mod order_eval;
+pub mod pricing;
+pub mod rewards;
+pub mod stall;
+pub mod wheat;
+
+use crate::api::crops::Farmland;
+use crate::api::market::MarketInfo;
+use crate::api::order::OrderInfo;
+use crate::api::panorama::FarmSnapshot;
+use crate::api::stall::StallInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What a strategy knows about the farm. Parts that were not queried are `None`,
+/// so a strategy only plans for the duties whose data it was given.
+#[derive(Default, Debug, Clone)]
+pub struct FarmState {
+    pub snapshot: Option<FarmSnapshot>,
+    /// Item counts by item id.
+    pub warehouse_items: HashMap<i64, i64>,
+    pub stall: Option<StallInfo>,
+    pub orders: Option<OrderInfo>,
+    pub market: Option<MarketInfo>,
+}
+
+impl From<FarmSnapshot> for FarmState {
+    fn from(snapshot: FarmSnapshot) -> Self {
+        Self {
+            warehouse_items: snapshot.warehouse_items(),
+            snapshot: Some(snapshot),
+            ..Default::default()
+        }
+    }
+}
+
+impl FarmState {
+    pub fn item_count(&self, item_id: i64) -> i64 {
+        self.warehouse_items
+            .get(&item_id)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// One thing the bot intends to do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Harvest {
+        item_id: i64,
+        field_ids: Vec<i64>,
+    },
+    Plant {
+        item_id: i64,
+        farmlands: Vec<Farmland>,
+    },
+    Onshelf {
+        slot: i64,
+        item_id: i64,
+        count: i64,
+        coin: i64,
+        ad: bool,
+    },
+    Earn {
+        slot: i64,
+        stall_sale_id: i64,
+    },
+    /// Sells to the system market.
+    Sell {
+        item_id: i64,
+        count: i64,
+    },
+    Accomplish {
+        order_id: i64,
+        /// Items the order takes from the warehouse.
+        items: Vec<(i64, i64)>,
+    },
+    Refuse {
+        order_id: i64,
+    },
+    /// Buys a market item by its market id.
+    Buy {
+        id: i64,
+        item_id: i64,
+        count: i64,
+    },
+}
+
+/// Decides what to do. Actions are executed in order.
+pub trait Strategy {
+    fn plan(&mut self, state: &FarmState) -> Vec<Action>;
+}
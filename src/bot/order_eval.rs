@@ -0,0 +1,220 @@
+use crate::api::order::Order;
+use crate::catalog::ItemCatalog;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// What to do with a pending order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderDecision {
+    /// The warehouse holds every item and the order pays off.
+    Accept,
+    /// The order pays off, but some crops have to be grown first.
+    Grow,
+    Refuse,
+}
+
+/// The outcome of [`OrderEvaluator::evaluate`], with the numbers behind it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEvaluation {
+    pub decision: OrderDecision,
+    /// Coins the order is worth: its coin plus exp, voucher and crystal in coins.
+    pub value: f64,
+    /// Coins the order costs: the market value of its items plus the field time
+    /// needed to grow the missing ones.
+    pub cost: f64,
+    /// Units missing from the warehouse, by item id.
+    pub missing: HashMap<i64, i64>,
+    /// How long growing the missing items takes, `0` when nothing is missing.
+    pub grow_time: Duration,
+    pub reason: String,
+}
+
+impl OrderEvaluation {
+    pub fn profit(&self) -> f64 {
+        self.value - self.cost
+    }
+}
+
+impl fmt::Display for OrderEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}, value: {:.1}, cost: {:.1}, {}",
+            self.decision, self.value, self.cost, self.reason
+        )
+    }
+}
+
+/// Scores orders by what they pay against what their items are worth.
+#[derive(Debug, Clone)]
+pub struct OrderEvaluator {
+    pub catalog: ItemCatalog,
+    /// Coins one exp point is worth.
+    pub exp_value: f64,
+    /// Coins a voucher reward is worth.
+    pub voucher_value: f64,
+    /// Coins a crystal reward is worth.
+    pub crystal_value: f64,
+    /// Coins a field earns per hour when it is not growing for an order.
+    pub field_hour_value: f64,
+    /// Units harvested per field.
+    pub harvest_yield: i64,
+    /// Orders whose missing items take longer to grow are refused.
+    pub max_grow_time: Duration,
+    /// Orders asking for more units in total are refused.
+    pub max_items: i64,
+    /// Smallest profit, in coins, for an order to be worth it.
+    pub min_profit: f64,
+}
+
+impl Default for OrderEvaluator {
+    fn default() -> Self {
+        Self::new(ItemCatalog::bundled())
+    }
+}
+
+impl OrderEvaluator {
+    pub fn new(catalog: ItemCatalog) -> Self {
+        Self {
+            catalog,
+            exp_value: 1.0,
+            voucher_value: 50.0,
+            crystal_value: 50.0,
+            field_hour_value: 10.0,
+            harvest_yield: 2,
+            max_grow_time: Duration::from_secs(3600),
+            max_items: 20,
+            min_profit: 0.0,
+        }
+    }
+
+    /// Evaluates a pending order against the warehouse counts in `items`.
+    pub fn evaluate(&self, order: &Order, items: &HashMap<i64, i64>) -> OrderEvaluation {
+        let mut value = order.coin as f64 + order.exp as f64 * self.exp_value;
+        if order.voucher_item_id != 0 {
+            value += self.voucher_value;
+        }
+        if order.crystal_item_id != 0 {
+            value += self.crystal_value;
+        }
+
+        let mut cost = 0.0;
+        let mut missing = HashMap::new();
+        let mut grow_time = Duration::ZERO;
+        let mut unknown = Vec::new();
+        let mut ungrowable = Vec::new();
+        for item in order.items.iter() {
+            let Some(def) = self.catalog.get(item.item_id) else {
+                unknown.push(item.item_id);
+                continue;
+            };
+            cost += def.sell_price * item.count as f64;
+            let short = item.count - items.get(&item.item_id).copied().unwrap_or_default();
+            if short <= 0 {
+                continue;
+            }
+            missing.insert(item.item_id, short);
+            match def.growth_time() {
+                Some(growth) => {
+                    let fields = (short + self.harvest_yield - 1) / self.harvest_yield.max(1);
+                    cost += fields as f64 * growth.as_secs_f64() / 3600.0 * self.field_hour_value;
+                    grow_time = grow_time.max(growth);
+                }
+                None => ungrowable.push(self.catalog.name(item.item_id)),
+            }
+        }
+
+        let total: i64 = order.items.iter().map(|item| item.count).sum();
+        let profit = value - cost;
+        let (decision, reason) = if !unknown.is_empty() {
+            (OrderDecision::Refuse, format!("unknown items {unknown:?}"))
+        } else if total > self.max_items {
+            (
+                OrderDecision::Refuse,
+                format!("{total} items is above {}", self.max_items),
+            )
+        } else if profit < self.min_profit {
+            (
+                OrderDecision::Refuse,
+                format!("profit {profit:.1} is below {:.1}", self.min_profit),
+            )
+        } else if missing.is_empty() {
+            (OrderDecision::Accept, format!("profit {profit:.1}"))
+        } else if !ungrowable.is_empty() {
+            (
+                OrderDecision::Refuse,
+                format!("missing {} can not be grown", ungrowable.join(", ")),
+            )
+        } else if grow_time > self.max_grow_time {
+            (
+                OrderDecision::Refuse,
+                format!(
+                    "growing takes {grow_time:?}, above {:?}",
+                    self.max_grow_time
+                ),
+            )
+        } else {
+            (
+                OrderDecision::Grow,
+                format!("profit {profit:.1} after growing for {grow_time:?}"),
+            )
+        };
+        OrderEvaluation {
+            decision,
+            value,
+            cost,
+            missing,
+            grow_time,
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::order::OrderItem;
+    use crate::catalog::tests::test_catalog;
+
+    fn order(coin: i64, items: &[(i64, i64)]) -> Order {
+        Order {
+            order_id: 1,
+            coin,
+            exp: 5,
+            items: items
+                .iter()
+                .map(|&(item_id, count)| OrderItem { item_id, count })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let evaluator = OrderEvaluator::new(test_catalog());
+        let items = HashMap::from([(201001, 10), (201002, 1)]);
+
+        let eval = evaluator.evaluate(&order(40, &[(201001, 2), (201002, 1)]), &items);
+        assert_eq!(eval.decision, OrderDecision::Accept, "{eval}");
+        assert_eq!(eval.cost, 4.0);
+        assert_eq!(eval.value, 45.0);
+
+        let eval = evaluator.evaluate(&order(40, &[(201002, 3)]), &items);
+        assert_eq!(eval.decision, OrderDecision::Grow, "{eval}");
+        assert_eq!(eval.missing, HashMap::from([(201002, 2)]));
+        assert_eq!(eval.grow_time, Duration::from_secs(300));
+
+        let eval = evaluator.evaluate(&order(0, &[(201001, 10)]), &items);
+        assert_eq!(eval.decision, OrderDecision::Refuse, "{eval}");
+
+        let eval = evaluator.evaluate(&order(400, &[(209001, 1)]), &items);
+        assert_eq!(eval.decision, OrderDecision::Refuse, "{eval}");
+        assert!(eval.reason.contains("can not be grown"), "{eval}");
+
+        let mut voucher = order(0, &[(201001, 2)]);
+        voucher.voucher_item_id = 310001;
+        let eval = evaluator.evaluate(&voucher, &items);
+        assert_eq!(eval.decision, OrderDecision::Accept, "{eval}");
+    }
+}
@@ -0,0 +1,159 @@
+use super::stall::StallObservation;
+use crate::catalog::ItemCatalog;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// What [`StallPricing`] optimizes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingGoal {
+    /// Starts halfway down the price range, drops quickly on stale listings and
+    /// climbs slowly.
+    SellFast,
+    /// Starts at the stall cap, drops slowly on stale listings and climbs quickly.
+    #[default]
+    MaxCoin,
+}
+
+impl PricingGoal {
+    fn start_factor(self) -> f64 {
+        match self {
+            Self::SellFast => 0.5,
+            Self::MaxCoin => 1.0,
+        }
+    }
+
+    /// Factor steps `(up, down)` after a fast sale and a stale listing.
+    fn steps(self) -> (f64, f64) {
+        match self {
+            Self::SellFast => (0.05, 0.2),
+            Self::MaxCoin => (0.1, 0.05),
+        }
+    }
+}
+
+/// Picks the count and coin of stall listings per item, at a factor of the stall cap
+/// that never goes below the market sell price.
+#[derive(Debug, Clone)]
+pub struct StallPricing {
+    pub catalog: ItemCatalog,
+    pub goal: PricingGoal,
+    /// Units per listing by item id, `default_count` for the rest.
+    pub counts: HashMap<i64, i64>,
+    pub default_count: i64,
+    pub target_sale_time: Duration,
+    factors: HashMap<i64, f64>,
+}
+
+impl StallPricing {
+    pub fn new(catalog: ItemCatalog, goal: PricingGoal) -> Self {
+        Self {
+            catalog,
+            goal,
+            counts: HashMap::new(),
+            default_count: 10,
+            target_sale_time: Duration::from_secs(3600),
+            factors: HashMap::new(),
+        }
+    }
+
+    /// Lowest factor of `item_id`: the market sell price.
+    fn floor(&self, item_id: i64) -> f64 {
+        self.catalog
+            .get(item_id)
+            .filter(|item| item.max_stall_price > 0.0)
+            .map_or(1.0, |item| {
+                (item.sell_price / item.max_stall_price).clamp(0.0, 1.0)
+            })
+    }
+
+    /// Current factor of the stall cap for `item_id`.
+    pub fn factor(&self, item_id: i64) -> f64 {
+        let floor = self.floor(item_id);
+        self.factors
+            .get(&item_id)
+            .copied()
+            .unwrap_or_else(|| floor + (1.0 - floor) * self.goal.start_factor())
+    }
+
+    fn adjust(&mut self, item_id: i64, delta: f64) {
+        let factor = (self.factor(item_id) + delta).clamp(self.floor(item_id), 1.0);
+        tracing::info!(
+            "stall price of {} at {:.0}% of the cap",
+            self.catalog.name(item_id),
+            factor * 100.0
+        );
+        self.factors.insert(item_id, factor);
+    }
+
+    /// `(count, coin)` of one listing of `item_id`, `None` for items the stall
+    /// does not take.
+    pub fn listing(&self, item_id: i64) -> Option<(i64, i64)> {
+        let item = self.catalog.get(item_id)?;
+        if item.max_stall_price <= 0.0 {
+            return None;
+        }
+        let count = self
+            .counts
+            .get(&item_id)
+            .copied()
+            .unwrap_or(self.default_count);
+        let max_coin = item.max_stall_coin(count);
+        // The epsilon keeps a factor like `1/3.6 * 3.6` from flooring one coin short.
+        let coin =
+            (item.max_stall_price * self.factor(item_id) * count as f64 + 1e-6).floor() as i64;
+        Some((count, coin.clamp(1, max_coin.max(1))))
+    }
+
+    /// Listings sold within `target_sale_time` raise their item's price, listings sold
+    /// later or gone stale lower it.
+    pub fn learn(&mut self, observation: &StallObservation) {
+        let (up, down) = self.goal.steps();
+        let target = self.target_sale_time.as_secs() as i64;
+        for &(item_id, secs) in observation.sold.iter() {
+            self.adjust(item_id, if secs > target { -down } else { up });
+        }
+        for &item_id in observation.stale.iter() {
+            self.adjust(item_id, -down);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::test_catalog;
+
+    #[test]
+    fn test_pricing() {
+        let mut pricing = StallPricing::new(test_catalog(), PricingGoal::MaxCoin);
+        assert_eq!(pricing.listing(201001), Some((10, 36)));
+
+        let stale = StallObservation {
+            stale: vec![201001],
+            ..Default::default()
+        };
+        pricing.learn(&stale);
+        assert_eq!(pricing.listing(201001), Some((10, 34)));
+        pricing.learn(&StallObservation {
+            sold: vec![(201001, 100)],
+            ..Default::default()
+        });
+        assert_eq!(pricing.listing(201001), Some((10, 36)));
+        pricing.learn(&StallObservation {
+            sold: vec![(201001, 3900)],
+            ..Default::default()
+        });
+        assert_eq!(pricing.listing(201001), Some((10, 34)));
+
+        let mut pricing = StallPricing::new(test_catalog(), PricingGoal::SellFast);
+        pricing.counts.insert(209001, 1);
+        assert_eq!(pricing.listing(209001), Some((1, 35)));
+        assert_eq!(pricing.listing(201001), Some((10, 23)));
+        pricing.learn(&stale);
+        pricing.learn(&stale);
+        // Never below the market price.
+        assert_eq!(pricing.listing(201001), Some((10, 10)));
+    }
+}
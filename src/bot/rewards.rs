@@ -0,0 +1,207 @@
+use crate::api::order::Order;
+use crate::error::{WeLoveError, WeLoveResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// An accomplished order whose reward is not claimed yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingReward {
+    pub order_id: i64,
+    /// Unix time the reward is claimed at.
+    pub due: i64,
+    /// Failed claims so far.
+    pub attempts: u32,
+}
+
+/// Rewards of accomplished orders, claimed once their delivery time has passed.
+///
+/// Failed claims are retried with a doubling delay. With a path, the queue is written
+/// to that JSON file on every change and read back by [`load`](Self::load), so rewards
+/// survive a restart.
+#[derive(Debug)]
+pub struct RewardQueue {
+    pub path: Option<PathBuf>,
+    /// Delay before the first retry of a failed claim.
+    pub retry: Duration,
+    /// Claims are given up after this many failures.
+    pub max_attempts: u32,
+    pending: Vec<PendingReward>,
+}
+
+impl Default for RewardQueue {
+    fn default() -> Self {
+        Self {
+            path: None,
+            retry: Duration::from_secs(60),
+            max_attempts: 8,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl RewardQueue {
+    /// An in-memory queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the queue saved at `path`, or starts an empty one there.
+    pub fn load(path: impl AsRef<Path>) -> WeLoveResult<Self> {
+        let path = path.as_ref();
+        let pending = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(WeLoveError::Other(format!(
+                    "failed to read reward queue {}: {e}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            pending,
+            ..Default::default()
+        })
+    }
+
+    pub fn pending(&self) -> &[PendingReward] {
+        &self.pending
+    }
+
+    pub fn contains(&self, order_id: i64) -> bool {
+        self.pending.iter().any(|r| r.order_id == order_id)
+    }
+
+    /// Queues the reward of `order_id`, due at unix time `due`.
+    pub fn push(&mut self, order_id: i64, due: i64) {
+        if self.contains(order_id) {
+            return;
+        }
+        self.pending.push(PendingReward {
+            order_id,
+            due,
+            attempts: 0,
+        });
+        self.save();
+    }
+
+    /// Queues the reward of every finished order, due at unix time `now`, and removes
+    /// them from `orders`. Returns whether a reward was queued.
+    pub fn queue_finished(&mut self, orders: &mut Vec<Order>, now: i64) -> bool {
+        let mut queued = false;
+        orders.retain(|o| {
+            if o.status != 1 {
+                return true;
+            }
+            if !self.contains(o.order_id) {
+                self.push(o.order_id, now);
+                queued = true;
+            }
+            false
+        });
+        queued
+    }
+
+    /// Order ids whose reward is due at unix time `now`.
+    pub fn due(&self, now: i64) -> Vec<i64> {
+        self.pending
+            .iter()
+            .filter(|r| r.due <= now)
+            .map(|r| r.order_id)
+            .collect()
+    }
+
+    /// Unix time of the next due reward.
+    pub fn next_due(&self) -> Option<i64> {
+        self.pending.iter().map(|r| r.due).min()
+    }
+
+    /// Removes a reward that was claimed.
+    pub fn claimed(&mut self, order_id: i64) {
+        self.pending.retain(|r| r.order_id != order_id);
+        self.save();
+    }
+
+    /// Reschedules a failed claim, or drops it once it failed `max_attempts` times.
+    /// The error is only logged: its result code tells too little to give up early.
+    pub fn failed(&mut self, order_id: i64, err: &WeLoveError, now: i64) {
+        let Some(index) = self.pending.iter().position(|r| r.order_id == order_id) else {
+            return;
+        };
+        let reward = &mut self.pending[index];
+        reward.attempts += 1;
+        if reward.attempts >= self.max_attempts {
+            tracing::error!(
+                "give up reward of order {order_id} after {} attempts: {err}",
+                reward.attempts
+            );
+            self.pending.remove(index);
+        } else {
+            let delay = self.retry.as_secs() << (reward.attempts - 1).min(16);
+            reward.due = now + delay as i64;
+            tracing::warn!("retry reward of order {order_id} in {delay}s: {err}");
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(&self.pending)
+            .map_err(WeLoveError::from)
+            .and_then(|json| {
+                std::fs::write(path, json).map_err(|e| WeLoveError::Other(e.to_string()))
+            });
+        if let Err(err) = result {
+            tracing::error!("failed to save reward queue {}: {err}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reward_queue() {
+        let path =
+            std::env::temp_dir().join(format!("welove520-rewards-{}.json", rand::random::<u64>()));
+        let mut queue = RewardQueue::load(&path).unwrap();
+        queue.push(1, 100);
+        queue.push(2, 110);
+        queue.push(1, 200);
+        assert_eq!(queue.due(105), [1]);
+        assert_eq!(queue.next_due(), Some(100));
+
+        queue.failed(1, &WeLoveError::Other("timeout".to_string()), 105);
+        assert_eq!(queue.due(105), Vec::<i64>::new());
+        assert_eq!(queue.next_due(), Some(110));
+        assert_eq!(queue.pending()[0].due, 165);
+
+        let mut queue = RewardQueue::load(&path).unwrap();
+        assert_eq!(queue.pending().len(), 2);
+        queue.claimed(2);
+        queue.failed(1, &WeLoveError::Other("gone".to_string()), 170);
+        assert_eq!(queue.pending()[0].due, 290);
+        queue.max_attempts = 3;
+        queue.failed(1, &WeLoveError::Other("gone".to_string()), 290);
+        assert!(queue.pending().is_empty());
+        assert!(RewardQueue::load(&path).unwrap().pending().is_empty());
+        std::fs::remove_file(path).unwrap();
+
+        let order = |order_id, status| Order {
+            order_id,
+            status,
+            ..Default::default()
+        };
+        let mut orders = vec![order(3, 1), order(4, 0)];
+        let mut queue = RewardQueue::new();
+        assert!(queue.queue_finished(&mut orders, 300));
+        assert_eq!(orders[0].order_id, 4);
+        assert_eq!(queue.due(300), [3]);
+        assert!(!queue.queue_finished(&mut vec![order(3, 1)], 400));
+    }
+}
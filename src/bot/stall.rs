@@ -0,0 +1,343 @@
+use super::Action;
+use crate::api::stall::StallInfo;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A listing on a stall slot.
+#[derive(Debug, Clone, PartialEq)]
+struct Listing {
+    item_id: i64,
+    /// Unix time the listing was put on shelf, or first seen.
+    since: i64,
+    stale: bool,
+}
+
+/// What [`StallManager::observe`] saw change on the stall.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StallObservation {
+    /// Item id and seconds on shelf of each listing seen sold before it went stale.
+    pub sold: Vec<(i64, i64)>,
+    /// Item id of each listing that went stale.
+    pub stale: Vec<i64>,
+}
+
+/// Decides what goes into each stall slot and which new listing gets the free ad.
+#[derive(Debug, Clone)]
+pub struct StallManager {
+    /// Items that may be listed.
+    pub item_ids: Vec<i64>,
+    /// Units of an item that are never listed, by item id.
+    pub keep: HashMap<i64, i64>,
+    /// Time between free ads; `0` only uses the free ad while none was used yet.
+    pub ad_cooldown: Duration,
+    /// Listings unsold this long after they were listed or last advertised are stale.
+    pub stale_after: Duration,
+    listings: HashMap<i64, Listing>,
+    /// Last ad time by slot, from the stall and from the ads placed here.
+    ad_times: HashMap<i64, i64>,
+}
+
+impl StallManager {
+    pub fn new(item_ids: Vec<i64>) -> Self {
+        Self {
+            item_ids,
+            keep: HashMap::new(),
+            ad_cooldown: Duration::ZERO,
+            stale_after: Duration::from_secs(3600),
+            listings: HashMap::new(),
+            ad_times: HashMap::new(),
+        }
+    }
+
+    /// Last ad time of `slot`, `0` when it never had one.
+    pub fn last_ad_time(&self, slot: i64) -> i64 {
+        self.ad_times.get(&slot).copied().unwrap_or_default()
+    }
+
+    /// Slots whose listing is stale.
+    pub fn stale_slots(&self) -> Vec<i64> {
+        let mut slots: Vec<_> = self
+            .listings
+            .iter()
+            .filter(|(_, listing)| listing.stale)
+            .map(|(slot, _)| *slot)
+            .collect();
+        slots.sort_unstable();
+        slots
+    }
+
+    /// Whether the free ad can be used at unix time `now`.
+    pub fn free_ad_ready(&self, stall: &StallInfo, now: i64) -> bool {
+        stall.last_free_ad_time == 0
+            || (!self.ad_cooldown.is_zero()
+                && now - stall.last_free_ad_time >= self.ad_cooldown.as_secs() as i64)
+    }
+
+    /// Tracks the age and ads of every listing at unix time `now`.
+    pub fn observe(&mut self, stall: &StallInfo, now: i64) -> StallObservation {
+        for item in stall
+            .stall_items
+            .iter()
+            .filter(|item| item.last_ad_time > 0)
+        {
+            let last = self.ad_times.entry(item.slot).or_default();
+            *last = (*last).max(item.last_ad_time);
+        }
+        self.listings
+            .retain(|slot, _| stall.stall_items.iter().any(|item| item.slot == *slot));
+
+        let mut observation = StallObservation::default();
+        let stale_after = self.stale_after.as_secs() as i64;
+        for item in stall.stall_items.iter() {
+            match item.status {
+                1 => {
+                    let last_ad_time = self.last_ad_time(item.slot);
+                    let listing = self.listings.entry(item.slot).or_insert(Listing {
+                        item_id: item.item_id,
+                        since: now,
+                        stale: false,
+                    });
+                    let unsold = now - listing.since.max(last_ad_time);
+                    if !listing.stale && unsold > stale_after {
+                        listing.stale = true;
+                        tracing::info!("stall slot {} unsold for {unsold}s", item.slot);
+                        observation.stale.push(item.item_id);
+                    }
+                }
+                2 => {
+                    if let Some(listing) = self.listings.remove(&item.slot) {
+                        if !listing.stale && listing.item_id == item.item_id {
+                            observation.sold.push((item.item_id, now - listing.since));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        observation
+    }
+
+    /// Earns sold slots and fills the free ones from `items`, which loses the listed
+    /// units. `listing` gives the `(count, coin)` of one listing of an item, or `None`
+    /// when the item is not listed. Call [`observe`](Self::observe) first.
+    pub fn plan(
+        &mut self,
+        stall: &StallInfo,
+        items: &mut HashMap<i64, i64>,
+        now: i64,
+        listing: impl Fn(i64) -> Option<(i64, i64)>,
+    ) -> Vec<Action> {
+        let mut actions = Vec::new();
+        let mut free_slots: Vec<_> = (1..=stall.capacity)
+            .filter(|slot| !stall.stall_items.iter().any(|item| item.slot == *slot))
+            .collect();
+        tracing::info!("stall empty_slots: {free_slots:?}");
+        for item in stall.stall_items.iter().filter(|item| item.status == 2) {
+            actions.push(Action::Earn {
+                slot: item.slot,
+                stall_sale_id: item.id,
+            });
+            free_slots.push(item.slot);
+        }
+        free_slots.sort_unstable();
+
+        let mut listings = Vec::new();
+        for slot in free_slots {
+            let best = self
+                .item_ids
+                .iter()
+                .filter_map(|&item_id| {
+                    let (count, coin) = listing(item_id)?;
+                    let surplus = items.get(&item_id).copied().unwrap_or_default()
+                        - self.keep.get(&item_id).copied().unwrap_or_default();
+                    (count > 0 && surplus >= count).then(|| {
+                        (
+                            item_id,
+                            count,
+                            coin,
+                            surplus as f64 * coin as f64 / count as f64,
+                        )
+                    })
+                })
+                .max_by(|a, b| a.3.total_cmp(&b.3));
+            let Some((item_id, count, coin, _)) = best else {
+                break;
+            };
+            *items.entry(item_id).or_default() -= count;
+            listings.push((slot, item_id, count, coin));
+        }
+
+        // The ad goes to the item with the most stale listings, then to the most valuable
+        // listing, on the slot that waited longest for one.
+        let stale = |item_id| {
+            self.listings
+                .values()
+                .filter(|listing| listing.stale && listing.item_id == item_id)
+                .count()
+        };
+        let ad_slot = self
+            .free_ad_ready(stall, now)
+            .then(|| {
+                listings
+                    .iter()
+                    .max_by_key(|(slot, item_id, _, coin)| {
+                        (stale(*item_id), *coin, -self.last_ad_time(*slot))
+                    })
+                    .map(|(slot, ..)| *slot)
+            })
+            .flatten();
+        if let Some(slot) = ad_slot {
+            self.ad_times.insert(slot, now);
+        }
+        for (slot, item_id, count, coin) in listings {
+            self.listings.insert(
+                slot,
+                Listing {
+                    item_id,
+                    since: now,
+                    stale: false,
+                },
+            );
+            actions.push(Action::Onshelf {
+                slot,
+                item_id,
+                count,
+                coin,
+                ad: ad_slot == Some(slot),
+            });
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::stall::StallItem;
+
+    #[test]
+    fn test_stall_manager() {
+        let mut manager = StallManager::new(vec![201001, 201002]);
+        let stall = StallInfo {
+            capacity: 3,
+            last_free_ad_time: 0,
+            stall_items: vec![
+                StallItem {
+                    id: 9,
+                    slot: 2,
+                    status: 2,
+                    last_ad_time: 50,
+                    ..Default::default()
+                },
+                StallItem {
+                    id: 8,
+                    slot: 3,
+                    status: 1,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let listing = |item_id| match item_id {
+            201001 => Some((10, 36)),
+            201002 => Some((5, 36)),
+            _ => None,
+        };
+        let mut items = HashMap::from([(201001, 25), (201002, 15)]);
+        manager.observe(&stall, 100);
+        let actions = manager.plan(&stall, &mut items, 100, listing);
+        let onshelf = |slot, item_id, count, ad| Action::Onshelf {
+            slot,
+            item_id,
+            count,
+            coin: 36,
+            ad,
+        };
+        assert_eq!(
+            actions,
+            [
+                Action::Earn {
+                    slot: 2,
+                    stall_sale_id: 9
+                },
+                onshelf(1, 201002, 5, true),
+                onshelf(2, 201001, 10, false),
+            ]
+        );
+        assert_eq!(items, HashMap::from([(201001, 15), (201002, 10)]));
+        assert_eq!(manager.last_ad_time(1), 100);
+        assert_eq!(manager.last_ad_time(2), 50);
+
+        let stall = StallInfo {
+            last_free_ad_time: 100,
+            ..stall
+        };
+        assert!(!manager.free_ad_ready(&stall, 1000));
+        manager.ad_cooldown = Duration::from_secs(600);
+        assert!(manager.free_ad_ready(&stall, 1000));
+
+        let mut manager = StallManager::new(vec![201002]);
+        manager.keep.insert(201002, 12);
+        let mut items = HashMap::from([(201002, 15)]);
+        let actions = manager.plan(&stall, &mut items, 1000, listing);
+        assert!(!actions
+            .iter()
+            .any(|action| matches!(action, Action::Onshelf { .. })));
+    }
+
+    #[test]
+    fn test_stale_listings() {
+        let mut manager = StallManager::new(vec![201001, 201002]);
+        manager.stale_after = Duration::from_secs(600);
+        let listed = |slot, status, last_ad_time| StallItem {
+            id: slot,
+            slot,
+            item_id: 201001,
+            status,
+            last_ad_time,
+            ..Default::default()
+        };
+        let mut stall = StallInfo {
+            capacity: 4,
+            stall_items: vec![listed(1, 1, 0), listed(2, 1, 500)],
+            ..Default::default()
+        };
+        assert_eq!(manager.observe(&stall, 0), StallObservation::default());
+        let observation = manager.observe(&stall, 700);
+        assert_eq!(observation.stale, [201001]);
+        assert_eq!(manager.stale_slots(), [1]);
+        assert!(manager.observe(&stall, 800).stale.is_empty());
+
+        let listing = |item_id| match item_id {
+            201001 => Some((10, 36)),
+            201002 => Some((5, 40)),
+            _ => None,
+        };
+        let mut items = HashMap::from([(201001, 10), (201002, 5)]);
+        let actions = manager.plan(&stall, &mut items, 800, listing);
+        assert_eq!(
+            actions,
+            [
+                Action::Onshelf {
+                    slot: 3,
+                    item_id: 201002,
+                    count: 5,
+                    coin: 40,
+                    ad: false,
+                },
+                Action::Onshelf {
+                    slot: 4,
+                    item_id: 201001,
+                    count: 10,
+                    coin: 36,
+                    ad: true,
+                },
+            ]
+        );
+
+        stall.stall_items = vec![listed(1, 2, 0), listed(2, 2, 500)];
+        let observation = manager.observe(&stall, 900);
+        assert_eq!(observation.sold, [(201001, 900)]);
+        assert!(manager.stale_slots().is_empty());
+    }
+}
@@ -0,0 +1,385 @@
+use super::crop_plan::CropPlan;
+use super::fulfillment::Fulfillment;
+use super::order_eval::{OrderDecision, OrderEvaluator};
+use super::pricing::StallPricing;
+use super::stall::StallManager;
+use super::{Action, FarmState, Strategy};
+use crate::api::crops::Farmland;
+use crate::api::panorama::Field;
+use crate::utils::unix_now;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+pub const WHEAT_ITEM_ID: i64 = 201001;
+
+/// Materials bought from the market by default.
+pub const BUY_ITEM_IDS: [i64; 8] = [
+    209001, 209002, 209003, 209004, 210001, 210002, 210003, 210004,
+];
+
+/// The default strategy: grow wheat, keep the stall stocked with it, sell the surplus,
+/// accomplish orders that pay off, grow what waiting orders miss and buy the materials
+/// listed in `buy_item_ids`.
+#[derive(Debug, Clone)]
+pub struct WheatStrategy {
+    pub crop_plan: CropPlan,
+    /// Item put on shelf and sold to the market.
+    pub item_id: i64,
+    /// Units per stall listing.
+    pub stall_count: i64,
+    /// Price of one stall listing.
+    pub stall_coin: i64,
+    /// Picks the listing count and price instead of `stall_count` and `stall_coin`.
+    pub pricing: Option<StallPricing>,
+    /// Fills the stall slots. Items other than `item_id` are only listed with `pricing`.
+    pub stall: StallManager,
+    /// Units kept when selling the surplus to the market.
+    pub keep: i64,
+    pub order_evaluator: OrderEvaluator,
+    pub buy_item_ids: Vec<i64>,
+    /// Stock and crops set aside by the last order plan.
+    pub fulfillment: Fulfillment,
+}
+
+impl Default for WheatStrategy {
+    fn default() -> Self {
+        Self {
+            crop_plan: CropPlan::single(WHEAT_ITEM_ID),
+            item_id: WHEAT_ITEM_ID,
+            stall_count: 10,
+            stall_coin: 36,
+            pricing: None,
+            stall: StallManager::new(vec![WHEAT_ITEM_ID]),
+            keep: 10,
+            order_evaluator: OrderEvaluator::default(),
+            buy_item_ids: BUY_ITEM_IDS.to_vec(),
+            fulfillment: Fulfillment::default(),
+        }
+    }
+}
+
+impl Strategy for WheatStrategy {
+    fn plan(&mut self, state: &FarmState) -> Vec<Action> {
+        let mut items = state.warehouse_items.clone();
+        let mut order_actions = Vec::new();
+        if state.orders.is_some() {
+            self.plan_orders(state, &mut items, &mut order_actions);
+        } else {
+            self.fulfillment.reserve(&mut items);
+        }
+        let mut actions = Vec::new();
+        if let Some(snapshot) = &state.snapshot {
+            self.plan_fields(&snapshot.fields, &mut actions);
+        }
+        actions.extend(order_actions);
+        if state.stall.is_some() {
+            self.plan_stall(state, &mut items, &mut actions);
+        }
+        if let Some(market) = &state.market {
+            for item in market
+                .market_item_list
+                .iter()
+                .filter(|item| self.buy_item_ids.contains(&item.item_id) && item.sold_out == 0)
+            {
+                actions.push(Action::Buy {
+                    id: item.id,
+                    item_id: item.item_id,
+                    count: item.count,
+                });
+            }
+        }
+        actions
+    }
+}
+
+impl WheatStrategy {
+    /// Harvests ready fields, one harvest per crop, then plants every free field:
+    /// crops waiting orders miss first, the crop plan for the rest.
+    fn plan_fields(&self, fields: &[Field], actions: &mut Vec<Action>) {
+        let mut ready_fields: BTreeMap<i64, Vec<&Field>> = BTreeMap::new();
+        for f in fields
+            .iter()
+            .filter(|f| f.plant_item_id != -1 && f.left_time < 0)
+        {
+            ready_fields.entry(f.plant_item_id).or_default().push(f);
+        }
+        let mut empty_fields: Vec<_> = fields.iter().filter(|f| f.plant_item_id == -1).collect();
+        for (item_id, harvest_fields) in ready_fields {
+            actions.push(Action::Harvest {
+                item_id,
+                field_ids: harvest_fields.iter().map(|f| f.id).collect(),
+            });
+            empty_fields.extend(harvest_fields);
+        }
+        empty_fields.sort_unstable_by(|a, b| match a.x.cmp(&b.x) {
+            Ordering::Equal => a.y.cmp(&b.y),
+            ord => ord,
+        });
+        let mut plant = BTreeMap::new();
+        let free = empty_fields
+            .iter()
+            .filter(|f| !self.crop_plan.fields.contains_key(&f.id))
+            .count();
+        for (item_id, count) in
+            self.fulfillment
+                .crops_to_plant(fields, self.order_evaluator.harvest_yield, free)
+        {
+            tracing::info!("plant {count} fields of {item_id} for waiting orders");
+            let mut taken = 0;
+            empty_fields.retain(|f| {
+                if taken < count && !self.crop_plan.fields.contains_key(&f.id) {
+                    plant.entry(item_id).or_insert_with(Vec::new).push(*f);
+                    taken += 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        for (item_id, plant_fields) in self.crop_plan.assign(&empty_fields) {
+            plant.entry(item_id).or_default().extend(plant_fields);
+        }
+        for (item_id, plant_fields) in plant {
+            if plant_fields.is_empty() {
+                continue;
+            }
+            actions.push(Action::Plant {
+                item_id,
+                farmlands: plant_fields
+                    .into_iter()
+                    .map(|f| Farmland {
+                        id: f.id,
+                        last_interval: 1,
+                        x: f.x,
+                        y: f.y,
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    /// Earns sold slots and fills the free ones through the [`StallManager`], then sells
+    /// the surplus.
+    fn plan_stall(
+        &mut self,
+        state: &FarmState,
+        items: &mut HashMap<i64, i64>,
+        actions: &mut Vec<Action>,
+    ) {
+        let Some(stall) = &state.stall else {
+            return;
+        };
+        let now = if stall.op_time > 0 {
+            stall.op_time
+        } else {
+            unix_now()
+        };
+        let observation = self.stall.observe(stall, now);
+        if let Some(pricing) = &mut self.pricing {
+            pricing.learn(&observation);
+        }
+        let pricing = &self.pricing;
+        let (item_id, stall_count, stall_coin) = (self.item_id, self.stall_count, self.stall_coin);
+        let stall_actions = self.stall.plan(stall, items, now, |id| match pricing {
+            Some(pricing) => pricing.listing(id),
+            None => (id == item_id).then_some((stall_count, stall_coin)),
+        });
+        actions.extend(stall_actions);
+
+        let count = items.get(&self.item_id).copied().unwrap_or_default();
+        if count > self.keep {
+            actions.push(Action::Sell {
+                item_id: self.item_id,
+                count: count - self.keep,
+            });
+            items.insert(self.item_id, self.keep);
+        }
+    }
+
+    /// Accomplishes orders the [`OrderEvaluator`] accepts and refuses the ones it
+    /// rejects. Finished orders are left to the [`RewardQueue`](super::rewards::RewardQueue). Orders worth growing for wait with their stock
+    /// reserved in [`fulfillment`](Self::fulfillment) until their crops are harvested.
+    fn plan_orders(
+        &mut self,
+        state: &FarmState,
+        items: &mut HashMap<i64, i64>,
+        actions: &mut Vec<Action>,
+    ) {
+        let Some(order_info) = &state.orders else {
+            return;
+        };
+        let mut fulfillment = Fulfillment::default();
+        for order in order_info.orders.iter() {
+            if order.status == 1 || order.time_left > 0 || order.items.is_empty() {
+                continue;
+            }
+            let evaluation = self.order_evaluator.evaluate(order, items);
+            tracing::info!(
+                "order {} in slot {}: {evaluation}",
+                order.order_id,
+                order.slot
+            );
+            match evaluation.decision {
+                OrderDecision::Accept => {
+                    for item in order.items.iter() {
+                        *items.entry(item.item_id).or_default() -= item.count;
+                    }
+                    actions.push(Action::Accomplish {
+                        order_id: order.order_id,
+                        items: order
+                            .items
+                            .iter()
+                            .map(|item| (item.item_id, item.count))
+                            .collect(),
+                    });
+                }
+                OrderDecision::Grow => {
+                    tracing::info!(
+                        "order {} waits for {:?}",
+                        order.order_id,
+                        evaluation.missing
+                    );
+                    fulfillment.add_order(order, items);
+                }
+                OrderDecision::Refuse => actions.push(Action::Refuse {
+                    order_id: order.order_id,
+                }),
+            }
+        }
+        self.fulfillment = fulfillment;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::order::OrderApi;
+    use crate::api::panorama::PanoramaApi;
+    use crate::api::stall::StallApi;
+    use crate::api::tests::get_mock_client;
+    use crate::bot::executor::Executor;
+    use crate::catalog::tests::test_catalog;
+
+    #[tokio::test]
+    async fn test_wheat_strategy() {
+        let (cli, farm) = get_mock_client();
+        let catalog = test_catalog();
+        let mut strategy = WheatStrategy {
+            order_evaluator: OrderEvaluator::new(catalog.clone()),
+            ..Default::default()
+        };
+        let mut state = FarmState::from(cli.snapshot().await.unwrap());
+        state.stall = Some(cli.stall_query().await.unwrap());
+        state.orders = Some(cli.order_query().await.unwrap());
+
+        let actions = strategy.plan(&state);
+        assert_eq!(
+            actions[..3],
+            [
+                Action::Harvest {
+                    item_id: 201001,
+                    field_ids: vec![1, 2],
+                },
+                Action::Harvest {
+                    item_id: 201002,
+                    field_ids: vec![4],
+                },
+                Action::Plant {
+                    item_id: 201001,
+                    farmlands: [(1, 10, 10), (2, 10, 11), (4, 11, 11), (5, 12, 10)]
+                        .into_iter()
+                        .map(|(id, x, y)| Farmland {
+                            id,
+                            last_interval: 1,
+                            x,
+                            y
+                        })
+                        .collect(),
+                },
+            ][..]
+        );
+        assert!(actions.contains(&Action::Accomplish {
+            order_id: 2434433067,
+            items: vec![(201001, 2), (201002, 3)],
+        }));
+
+        let mut warehouse_items = state.warehouse_items.clone();
+        let outcomes = Executor::new(&cli, &catalog)
+            .execute(actions, &mut warehouse_items)
+            .await;
+        for (action, result) in outcomes.iter() {
+            assert!(result.is_ok(), "{action:?}: {result:?}");
+        }
+        let farm = farm.lock().unwrap();
+        assert_eq!(warehouse_items, farm.warehouse);
+        assert!(farm.fields.iter().all(|f| f.plant_item_id != -1));
+    }
+
+    #[test]
+    fn test_grow_for_order() {
+        use crate::api::order::{Order, OrderInfo, OrderItem};
+        use crate::api::panorama::FarmSnapshot;
+        use crate::api::stall::StallInfo;
+
+        let mut strategy = WheatStrategy {
+            order_evaluator: OrderEvaluator::new(test_catalog()),
+            ..Default::default()
+        };
+        let fields = (1..=3)
+            .map(|id| Field {
+                id,
+                x: id,
+                plant_item_id: -1,
+                ..Default::default()
+            })
+            .collect();
+        let order = Order {
+            order_id: 7,
+            coin: 60,
+            items: vec![OrderItem {
+                item_id: 201002,
+                count: 3,
+            }],
+            ..Default::default()
+        };
+        let mut state = FarmState::from(FarmSnapshot {
+            fields,
+            ..Default::default()
+        });
+        state.warehouse_items = HashMap::from([(WHEAT_ITEM_ID, 10), (201002, 1)]);
+        state.orders = Some(OrderInfo {
+            orders: vec![order],
+            ..Default::default()
+        });
+        let actions = strategy.plan(&state);
+        let plant = |item_id, ids: &[i64]| Action::Plant {
+            item_id,
+            farmlands: ids
+                .iter()
+                .map(|&id| Farmland {
+                    id,
+                    last_interval: 1,
+                    x: id,
+                    y: 0,
+                })
+                .collect(),
+        };
+        assert_eq!(
+            actions,
+            [plant(WHEAT_ITEM_ID, &[2, 3]), plant(201002, &[1])]
+        );
+        assert_eq!(strategy.fulfillment.order_ids, [7]);
+
+        // A stall plan without the orders keeps the corn for the order.
+        state.warehouse_items.insert(201002, 3);
+        state.orders = None;
+        state.snapshot = None;
+        strategy.item_id = 201002;
+        strategy.stall_count = 1;
+        state.stall = Some(StallInfo {
+            capacity: 1,
+            ..Default::default()
+        });
+        assert!(strategy.plan(&state).is_empty());
+    }
+}
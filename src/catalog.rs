@@ -0,0 +1,205 @@
+use crate::error::{WeLoveError, WeLoveResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const BUNDLED_ITEMS: &str = include_str!("../data/items.json");
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemDef {
+    pub item_id: i64,
+    pub name: String,
+    pub pinyin: String,
+    pub english: String,
+    /// Warehouse category the item is stored in.
+    pub category: i64,
+    /// Growth time of a crop, `0` for items that are not planted.
+    pub growth_secs: i64,
+    /// Coins per unit when sold to the system market.
+    pub sell_price: f64,
+    /// Highest coins per unit the stall accepts.
+    pub max_stall_price: f64,
+    /// Where the values come from.
+    pub source: String,
+}
+
+impl ItemDef {
+    pub fn is_crop(&self) -> bool {
+        self.growth_secs > 0
+    }
+
+    pub fn growth_time(&self) -> Option<Duration> {
+        self.is_crop()
+            .then(|| Duration::from_secs(self.growth_secs as u64))
+    }
+
+    /// Highest coin price the stall accepts for `count` units.
+    pub fn max_stall_coin(&self, count: i64) -> i64 {
+        (self.max_stall_price * count as f64).floor() as i64
+    }
+}
+
+/// A partial [`ItemDef`]; only the fields that are set replace the catalog values.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemOverride {
+    pub item_id: i64,
+    pub name: Option<String>,
+    pub pinyin: Option<String>,
+    pub english: Option<String>,
+    pub category: Option<i64>,
+    pub growth_secs: Option<i64>,
+    pub sell_price: Option<f64>,
+    pub max_stall_price: Option<f64>,
+}
+
+/// Static item data: names, warehouse category, growth time and prices.
+#[derive(Debug, Clone)]
+pub struct ItemCatalog {
+    items: HashMap<i64, ItemDef>,
+}
+
+impl Default for ItemCatalog {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+impl ItemCatalog {
+    /// The catalog shipped in `data/items.json`.
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_ITEMS).expect("bundled items.json is invalid")
+    }
+
+    pub fn from_json(json: &str) -> WeLoveResult<Self> {
+        let items: Vec<ItemDef> = serde_json::from_str(json)?;
+        Ok(Self {
+            items: items.into_iter().map(|item| (item.item_id, item)).collect(),
+        })
+    }
+
+    /// Applies overrides on top of the catalog. Unknown item ids are added.
+    pub fn apply_overrides(&mut self, overrides: Vec<ItemOverride>) {
+        for o in overrides {
+            let item = self.items.entry(o.item_id).or_insert_with(|| ItemDef {
+                item_id: o.item_id,
+                ..Default::default()
+            });
+            if let Some(name) = o.name {
+                item.name = name;
+            }
+            if let Some(pinyin) = o.pinyin {
+                item.pinyin = pinyin;
+            }
+            if let Some(english) = o.english {
+                item.english = english;
+            }
+            if let Some(category) = o.category {
+                item.category = category;
+            }
+            if let Some(growth_secs) = o.growth_secs {
+                item.growth_secs = growth_secs;
+            }
+            if let Some(sell_price) = o.sell_price {
+                item.sell_price = sell_price;
+            }
+            if let Some(max_stall_price) = o.max_stall_price {
+                item.max_stall_price = max_stall_price;
+            }
+        }
+    }
+
+    /// Reads a JSON list of [`ItemOverride`] and applies it.
+    pub fn load_overrides(&mut self, path: impl AsRef<Path>) -> WeLoveResult<()> {
+        let json = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            WeLoveError::Config(format!(
+                "failed to read item overrides {}: {e}",
+                path.as_ref().display()
+            ))
+        })?;
+        self.apply_overrides(serde_json::from_str(&json)?);
+        Ok(())
+    }
+
+    pub fn get(&self, item_id: i64) -> Option<&ItemDef> {
+        self.items.get(&item_id)
+    }
+
+    /// Display name for logs, e.g. `小麦(201001)`. Unknown items show only the id.
+    pub fn name(&self, item_id: i64) -> String {
+        match self.get(item_id) {
+            Some(item) if !item.name.is_empty() => format!("{}({item_id})", item.name),
+            _ => item_id.to_string(),
+        }
+    }
+
+    pub fn growth_time(&self, item_id: i64) -> Option<Duration> {
+        self.get(item_id).and_then(ItemDef::growth_time)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &ItemDef> {
+        self.items.values()
+    }
+
+    pub fn crops(&self) -> impl Iterator<Item = &ItemDef> {
+        self.items().filter(|item| item.is_crop())
+    }
+
+    pub fn by_category(&self, category: i64) -> impl Iterator<Item = &ItemDef> {
+        self.items().filter(move |item| item.category == category)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Made-up crops and materials for tests that need more items than the bundled catalog.
+    const TEST_ITEMS: &str = r#"[
+        { "item_id": 201001, "name": "小麦", "category": 1, "growth_secs": 120, "sell_price": 1.0, "max_stall_price": 3.6 },
+        { "item_id": 201002, "name": "玉米", "category": 1, "growth_secs": 300, "sell_price": 2.0, "max_stall_price": 7.2 },
+        { "item_id": 201003, "category": 1, "growth_secs": 600, "sell_price": 2.0, "max_stall_price": 7.2 },
+        { "item_id": 209001, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 209002, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 209003, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 209004, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 210001, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 210002, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 210003, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 },
+        { "item_id": 210004, "category": 2, "sell_price": 10.0, "max_stall_price": 60.0 }
+    ]"#;
+
+    pub fn test_catalog() -> ItemCatalog {
+        ItemCatalog::from_json(TEST_ITEMS).unwrap()
+    }
+
+    #[test]
+    fn test_bundled() {
+        let catalog = ItemCatalog::bundled();
+        let wheat = catalog.get(201001).unwrap();
+        assert_eq!(wheat.english, "wheat");
+        assert_eq!(wheat.max_stall_coin(10), 36);
+        assert_eq!(catalog.growth_time(201001), Some(Duration::from_secs(125)));
+        assert_eq!(catalog.growth_time(209001), None);
+        assert!(catalog.items().all(|item| !item.source.is_empty()));
+        assert_eq!(catalog.name(201001), "小麦(201001)");
+        assert_eq!(catalog.name(1), "1");
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut catalog = ItemCatalog::bundled();
+        catalog.apply_overrides(
+            serde_json::from_str(
+                r#"[{"item_id": 201001, "max_stall_price": 3.0}, {"item_id": 299001, "name": "new", "growth_secs": 60}]"#,
+            )
+            .unwrap(),
+        );
+        let wheat = catalog.get(201001).unwrap();
+        assert_eq!(wheat.max_stall_coin(10), 30);
+        assert_eq!(wheat.name, "小麦");
+        assert!(catalog.get(299001).unwrap().is_crop());
+    }
+}
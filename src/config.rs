@@ -0,0 +1,591 @@
+use crate::api::builder::WeLoveClientBuilder;
+use crate::api::WeLoveClient;
+use crate::bot::crop_plan::CropPlan;
+use crate::bot::fulfillment::Fulfillment;
+use crate::bot::order_eval::OrderEvaluator;
+use crate::bot::pricing::{PricingGoal, StallPricing};
+use crate::bot::rewards::RewardQueue;
+use crate::bot::stall::StallManager;
+use crate::bot::wheat::{WheatStrategy, BUY_ITEM_IDS, WHEAT_ITEM_ID};
+use crate::catalog::ItemCatalog;
+use crate::error::{WeLoveError, WeLoveResult};
+use crate::timeline::Timeline;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// Bot configuration, read from a TOML file. See `welove520.example.toml`.
+///
+/// Every section is optional and falls back to the built-in wheat behavior.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Plans and logs actions without changing the farm, see [`WeLoveClient::dry_run`].
+    pub dry_run: bool,
+    pub account: AccountConfig,
+    pub crops: CropsConfig,
+    pub stall: StallConfig,
+    pub reserve: ReserveConfig,
+    pub orders: OrdersConfig,
+    pub market: MarketConfig,
+    pub timing: TimingConfig,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountConfig {
+    pub base_url: String,
+    /// App version sent as the `fv` param.
+    pub version: String,
+    pub union_id: String,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CropsConfig {
+    /// Crops planted in free fields, split by weight. May be empty when `fields` is set,
+    /// then fields not listed there stay empty.
+    pub weights: Vec<CropWeight>,
+    /// Fields that always grow the same crop.
+    pub fields: Vec<FieldCrop>,
+    /// Units harvested per field.
+    pub harvest_yield: i64,
+}
+
+impl Default for CropsConfig {
+    fn default() -> Self {
+        Self {
+            weights: vec![CropWeight {
+                item_id: WHEAT_ITEM_ID,
+                weight: 1,
+            }],
+            fields: Vec::new(),
+            harvest_yield: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CropWeight {
+    pub item_id: i64,
+    pub weight: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldCrop {
+    pub field_id: i64,
+    pub item_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StallConfig {
+    pub item_id: i64,
+    /// Units per listing.
+    pub count: i64,
+    /// Fixed price of one listing. Without it the price follows how fast listings sell.
+    pub coin: Option<i64>,
+    /// What the dynamic price aims for.
+    pub goal: PricingGoal,
+    /// Listings unsold for longer are stale. Faster sales raise the dynamic price, stale
+    /// and slower ones lower it.
+    pub target_sale_secs: u64,
+    /// Other items listed from their surplus, with the dynamic price.
+    pub items: Vec<StallItemConfig>,
+    /// Time between free ads; `0` only uses the free ad while none was used yet.
+    pub ad_cooldown_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StallItemConfig {
+    pub item_id: i64,
+    /// Units per listing.
+    pub count: i64,
+}
+
+impl Default for StallConfig {
+    fn default() -> Self {
+        Self {
+            item_id: WHEAT_ITEM_ID,
+            count: 10,
+            coin: None,
+            goal: PricingGoal::default(),
+            target_sale_secs: 3600,
+            items: Vec::new(),
+            ad_cooldown_secs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReserveConfig {
+    /// Units of the stall item kept when the surplus is sold to the market, and units of
+    /// each of `stall.items` never put on the stall.
+    pub keep: i64,
+}
+
+impl Default for ReserveConfig {
+    fn default() -> Self {
+        Self { keep: 10 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrdersConfig {
+    /// Orders asking for more units in total are refused.
+    pub max_items: i64,
+    /// Coins one exp point is worth.
+    pub exp_value: f64,
+    /// Coins a voucher reward is worth.
+    pub voucher_value: f64,
+    /// Coins a crystal reward is worth.
+    pub crystal_value: f64,
+    /// Coins a field earns per hour when it is not growing for an order.
+    pub field_hour_value: f64,
+    /// Orders whose missing items take longer to grow are refused.
+    pub max_grow_secs: u64,
+    /// Smallest profit, in coins, for an order to be worth it.
+    pub min_profit: f64,
+    /// File the unclaimed rewards are kept in across restarts; empty keeps them in memory,
+    /// as does a dry run.
+    pub reward_queue: String,
+}
+
+impl Default for OrdersConfig {
+    fn default() -> Self {
+        Self {
+            max_items: 20,
+            exp_value: 1.0,
+            voucher_value: 50.0,
+            crystal_value: 50.0,
+            field_hour_value: 10.0,
+            max_grow_secs: 3600,
+            min_profit: 0.0,
+            reward_queue: "welove520.rewards.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarketConfig {
+    /// Item ids bought whenever the market offers them.
+    pub buy: Vec<i64>,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self {
+            buy: BUY_ITEM_IDS.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    /// Delay before a job retries after a failed query.
+    pub retry_secs: u64,
+    pub min_sleep_secs: u64,
+    pub max_sleep_secs: u64,
+    /// How often a stall with items on sale is polled.
+    pub stall_poll_secs: u64,
+    /// Delay between accomplishing an order and claiming its reward.
+    pub reward_delay_secs: u64,
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            retry_secs: 125,
+            min_sleep_secs: 5,
+            max_sleep_secs: 600,
+            stall_poll_secs: 300,
+            reward_delay_secs: 10,
+        }
+    }
+}
+
+impl TimingConfig {
+    pub fn retry(&self) -> Duration {
+        Duration::from_secs(self.retry_secs)
+    }
+
+    pub fn reward_delay(&self) -> Duration {
+        Duration::from_secs(self.reward_delay_secs)
+    }
+}
+
+impl Config {
+    pub fn from_toml(s: &str) -> WeLoveResult<Self> {
+        toml::from_str(s).map_err(|e| WeLoveError::Config(format!("invalid config: {e}")))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> WeLoveResult<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path).map_err(|e| {
+            WeLoveError::Config(format!("failed to read config {}: {e}", path.display()))
+        })?;
+        Self::from_toml(&s)
+    }
+
+    /// Fills unset account fields from `BASE_URL`, `VERSION`, `UNION_ID`, `USER_AGENT`
+    /// and `PROXY`, so credentials can stay out of the file.
+    pub fn account_from_env(&mut self) {
+        let account = &mut self.account;
+        for (value, key) in [
+            (&mut account.base_url, "BASE_URL"),
+            (&mut account.version, "VERSION"),
+            (&mut account.union_id, "UNION_ID"),
+        ] {
+            if value.is_empty() {
+                if let Ok(v) = std::env::var(key) {
+                    *value = v;
+                }
+            }
+        }
+        for (value, key) in [
+            (&mut account.user_agent, "USER_AGENT"),
+            (&mut account.proxy, "PROXY"),
+        ] {
+            if value.is_none() {
+                *value = std::env::var(key).ok();
+            }
+        }
+    }
+
+    /// Checks the config against the catalog and reports every problem at once.
+    pub fn validate(&self, catalog: &ItemCatalog) -> WeLoveResult<()> {
+        let mut errors = Vec::new();
+        for (value, key) in [
+            (&self.account.base_url, "account.base_url"),
+            (&self.account.version, "account.version"),
+            (&self.account.union_id, "account.union_id"),
+        ] {
+            if value.is_empty() {
+                errors.push(format!("{key} is not set"));
+            }
+        }
+
+        let is_crop = |item_id| catalog.get(item_id).is_some_and(|item| item.is_crop());
+        for w in self.crops.weights.iter() {
+            if !is_crop(w.item_id) {
+                errors.push(format!("crops.weights: {} is not a crop", w.item_id));
+            }
+        }
+        // Without weights only the pinned fields are planted, so they are optional then.
+        if self.crops.fields.is_empty() && self.crops.weights.iter().all(|w| w.weight == 0) {
+            errors.push("crops.weights: at least one weight must be above 0".to_string());
+        }
+        if self.crops.harvest_yield <= 0 {
+            errors.push("crops.harvest_yield must be above 0".to_string());
+        }
+        for f in self.crops.fields.iter() {
+            if !is_crop(f.item_id) {
+                errors.push(format!(
+                    "crops.fields: {} for field {} is not a crop",
+                    f.item_id, f.field_id
+                ));
+            }
+        }
+
+        match catalog.get(self.stall.item_id) {
+            None => errors.push(format!(
+                "stall.item_id: unknown item {}",
+                self.stall.item_id
+            )),
+            Some(item) => {
+                if self.stall.count <= 0 {
+                    errors.push("stall.count must be above 0".to_string());
+                }
+                match self.stall.coin {
+                    Some(coin) if coin <= 0 => {
+                        errors.push("stall.coin must be above 0".to_string())
+                    }
+                    Some(coin) if item.max_stall_price > 0.0 => {
+                        let max = item.max_stall_coin(self.stall.count);
+                        if coin > max {
+                            errors.push(format!(
+                                "stall.coin: {coin} is above the highest price {max} for {} x{}",
+                                catalog.name(item.item_id),
+                                self.stall.count
+                            ));
+                        }
+                    }
+                    None if item.max_stall_price <= 0.0 => errors.push(format!(
+                        "stall.coin must be set, {} has no stall price",
+                        catalog.name(item.item_id)
+                    )),
+                    _ => {}
+                }
+            }
+        }
+
+        for item in self.stall.items.iter() {
+            match catalog.get(item.item_id) {
+                None => errors.push(format!("stall.items: unknown item {}", item.item_id)),
+                Some(def) if def.max_stall_price <= 0.0 => errors.push(format!(
+                    "stall.items: {} has no stall price",
+                    catalog.name(item.item_id)
+                )),
+                Some(_) if item.count <= 0 => errors.push(format!(
+                    "stall.items: count of {} must be above 0",
+                    item.item_id
+                )),
+                _ => {}
+            }
+        }
+        if !self.stall.items.is_empty() && self.stall.coin.is_some() {
+            errors
+                .push("stall.items need the dynamic price, stall.coin must not be set".to_string());
+        }
+        if self.stall.target_sale_secs == 0 {
+            errors.push("stall.target_sale_secs must be above 0".to_string());
+        }
+        if self.reserve.keep < 0 {
+            errors.push("reserve.keep must not be negative".to_string());
+        }
+        if self.orders.max_items <= 0 {
+            errors.push("orders.max_items must be above 0".to_string());
+        }
+        for (value, key) in [
+            (self.orders.exp_value, "orders.exp_value"),
+            (self.orders.voucher_value, "orders.voucher_value"),
+            (self.orders.crystal_value, "orders.crystal_value"),
+            (self.orders.field_hour_value, "orders.field_hour_value"),
+        ] {
+            if value < 0.0 {
+                errors.push(format!("{key} must not be negative"));
+            }
+        }
+        for item_id in self.market.buy.iter() {
+            if catalog.get(*item_id).is_none() {
+                errors.push(format!("market.buy: unknown item {item_id}"));
+            }
+        }
+
+        let t = &self.timing;
+        for (value, key) in [
+            (t.retry_secs, "timing.retry_secs"),
+            (t.min_sleep_secs, "timing.min_sleep_secs"),
+            (t.max_sleep_secs, "timing.max_sleep_secs"),
+            (t.stall_poll_secs, "timing.stall_poll_secs"),
+            (t.reward_delay_secs, "timing.reward_delay_secs"),
+        ] {
+            if value == 0 {
+                errors.push(format!("{key} must be above 0"));
+            }
+        }
+        if t.min_sleep_secs > t.max_sleep_secs {
+            errors.push("timing.min_sleep_secs is above timing.max_sleep_secs".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(WeLoveError::Config(errors.join("; ")))
+        }
+    }
+
+    /// A client builder with the account settings.
+    pub fn client_builder(&self) -> WeLoveClientBuilder {
+        let account = &self.account;
+        let mut builder = WeLoveClient::builder()
+            .base_url(&account.base_url)
+            .fv(&account.version)
+            .union_id(&account.union_id)
+            .dry_run(self.dry_run);
+        if let Some(user_agent) = &account.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(proxy) = &account.proxy {
+            builder = builder.proxy(proxy);
+        }
+        builder
+    }
+
+    pub fn crop_plan(&self) -> CropPlan {
+        CropPlan {
+            fields: self
+                .crops
+                .fields
+                .iter()
+                .map(|f| (f.field_id, f.item_id))
+                .collect(),
+            weights: self
+                .crops
+                .weights
+                .iter()
+                .map(|w| (w.item_id, w.weight))
+                .collect(),
+        }
+    }
+
+    pub fn strategy(&self, catalog: &ItemCatalog) -> WheatStrategy {
+        let stall_coin = self.stall.coin.unwrap_or_else(|| {
+            catalog
+                .get(self.stall.item_id)
+                .map_or(0, |item| item.max_stall_coin(self.stall.count))
+        });
+        WheatStrategy {
+            crop_plan: self.crop_plan(),
+            item_id: self.stall.item_id,
+            stall_count: self.stall.count,
+            stall_coin,
+            pricing: self.stall.coin.is_none().then(|| self.pricing(catalog)),
+            stall: self.stall_manager(),
+            keep: self.reserve.keep,
+            order_evaluator: self.order_evaluator(catalog),
+            buy_item_ids: self.market.buy.clone(),
+            fulfillment: Fulfillment::default(),
+        }
+    }
+
+    /// Dynamic stall pricing for the stall item.
+    pub fn pricing(&self, catalog: &ItemCatalog) -> StallPricing {
+        let mut pricing = StallPricing::new(catalog.clone(), self.stall.goal);
+        pricing.counts.insert(self.stall.item_id, self.stall.count);
+        for item in self.stall.items.iter() {
+            pricing.counts.insert(item.item_id, item.count);
+        }
+        pricing.target_sale_time = Duration::from_secs(self.stall.target_sale_secs);
+        pricing
+    }
+
+    pub fn stall_manager(&self) -> StallManager {
+        let mut item_ids = vec![self.stall.item_id];
+        item_ids.extend(self.stall.items.iter().map(|item| item.item_id));
+        let mut manager = StallManager::new(item_ids);
+        for item in self.stall.items.iter() {
+            manager.keep.insert(item.item_id, self.reserve.keep);
+        }
+        manager.ad_cooldown = Duration::from_secs(self.stall.ad_cooldown_secs);
+        manager.stale_after = Duration::from_secs(self.stall.target_sale_secs);
+        manager
+    }
+
+    pub fn order_evaluator(&self, catalog: &ItemCatalog) -> OrderEvaluator {
+        let o = &self.orders;
+        OrderEvaluator {
+            catalog: catalog.clone(),
+            exp_value: o.exp_value,
+            voucher_value: o.voucher_value,
+            crystal_value: o.crystal_value,
+            field_hour_value: o.field_hour_value,
+            max_grow_time: Duration::from_secs(o.max_grow_secs),
+            max_items: o.max_items,
+            min_profit: o.min_profit,
+            harvest_yield: self.crops.harvest_yield,
+        }
+    }
+
+    /// The reward queue, loaded from `orders.reward_queue` when it is set. A dry run
+    /// keeps it in memory, so its pretended orders never reach the file.
+    pub fn reward_queue(&self) -> WeLoveResult<RewardQueue> {
+        let mut queue = if self.orders.reward_queue.is_empty() || self.dry_run {
+            RewardQueue::new()
+        } else {
+            RewardQueue::load(&self.orders.reward_queue)?
+        };
+        queue.retry = self.timing.retry();
+        Ok(queue)
+    }
+
+    /// An empty timeline with the configured sleep bounds.
+    pub fn timeline(&self) -> Timeline {
+        let mut timeline = Timeline::new();
+        timeline.min_sleep = Duration::from_secs(self.timing.min_sleep_secs);
+        timeline.max_sleep = Duration::from_secs(self.timing.max_sleep_secs);
+        timeline.stall_poll = Duration::from_secs(self.timing.stall_poll_secs);
+        timeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::test_catalog;
+
+    #[test]
+    fn test_example_config() {
+        let catalog = ItemCatalog::bundled();
+        let mut config = Config::from_toml(include_str!("../welove520.example.toml")).unwrap();
+        assert!(config.validate(&catalog).is_err());
+        config.account.base_url = "http://localhost".to_string();
+        config.account.version = "1".to_string();
+        config.account.union_id = "1".to_string();
+        config.validate(&catalog).unwrap();
+        let strategy = config.strategy(&catalog);
+        assert_eq!(strategy.stall_coin, 36);
+        let pricing = strategy.pricing.unwrap();
+        assert_eq!(pricing.goal, PricingGoal::MaxCoin);
+        assert_eq!(pricing.listing(201001), Some((10, 36)));
+        assert_eq!(strategy.stall.item_ids, [201001]);
+        assert_eq!(strategy.crop_plan.weights, [(201001, 1)]);
+        assert_eq!(strategy.order_evaluator.harvest_yield, 2);
+        config.orders.reward_queue = "rewards.json".to_string();
+        config.dry_run = true;
+        assert!(config.reward_queue().unwrap().path.is_none());
+        assert_eq!(config.timeline().max_sleep, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let catalog = test_catalog();
+        let err = Config::from_toml("[stall]\nprice = 1").unwrap_err();
+        assert!(err.to_string().contains("price"), "{err}");
+
+        let config = Config::from_toml(
+            r#"
+            [account]
+            base_url = "http://localhost"
+            version = "1"
+
+            [crops]
+            weights = [{ item_id = 209001, weight = 1 }]
+
+            [stall]
+            coin = 40
+
+            [timing]
+            reward_delay_secs = 0
+            "#,
+        )
+        .unwrap();
+        let err = config.validate(&catalog).unwrap_err().to_string();
+        assert!(err.contains("account.union_id is not set"), "{err}");
+        assert!(!err.contains("at least one weight"), "{err}");
+        assert!(
+            err.contains("timing.reward_delay_secs must be above 0"),
+            "{err}"
+        );
+        assert!(err.contains("crops.weights: 209001 is not a crop"), "{err}");
+        assert!(
+            err.contains("stall.coin: 40 is above the highest price 36"),
+            "{err}"
+        );
+
+        let mut config = Config::from_toml(
+            r#"
+            [crops]
+            weights = []
+            fields = [{ field_id = 1, item_id = 201002 }]
+            "#,
+        )
+        .unwrap();
+        config.account.base_url = "http://localhost".to_string();
+        config.account.version = "1".to_string();
+        config.account.union_id = "1".to_string();
+        config.validate(&catalog).unwrap();
+        config.crops.fields.clear();
+        let err = config.validate(&catalog).unwrap_err().to_string();
+        assert!(err.contains("at least one weight must be above 0"), "{err}");
+    }
+}
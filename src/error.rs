@@ -10,6 +10,12 @@ pub enum WeLoveError {
     Serde(#[from] serde_json::Error),
     #[error("none_error: {0}")]
     None(&'static str),
+    #[error("not_enough_items: {0}")]
+    NotEnoughItems(String),
+    #[error("server_error: result={result}, {msg}")]
+    Server { result: u32, msg: String },
+    #[error("config_error: {0}")]
+    Config(String),
     #[error("other_error: {0}")]
     Other(String),
 }
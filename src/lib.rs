@@ -1,5 +1,19 @@
 #![allow(incomplete_features)]
+#![allow(stable_features, async_fn_in_trait)]
 #![feature(async_fn_in_trait)]
 pub mod api;
+pub mod bot;
+pub mod catalog;
+pub mod config;
 pub mod error;
+pub mod scheduler;
+pub mod sim;
+pub mod timeline;
 pub mod utils;
+
+// Lets the exported macros name the crate and its serde dependencies from any crate.
+extern crate self as welove520;
+#[doc(hidden)]
+pub use serde;
+#[doc(hidden)]
+pub use serde_json;
@@ -0,0 +1,173 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Duration> + Send>>;
+
+/// Wakes the jobs [triggered by](Job::triggered_by) it before their delay is over.
+#[derive(Clone)]
+pub struct Trigger(Arc<watch::Sender<()>>);
+
+impl Default for Trigger {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(()).0))
+    }
+}
+
+impl Trigger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fire(&self) {
+        self.0.send_replace(());
+    }
+}
+
+/// A duty that runs again after the delay returned by its last run.
+pub struct Job {
+    name: String,
+    run: Box<dyn FnMut() -> JobFuture + Send>,
+    fired: Option<watch::Receiver<()>>,
+    wait_for_trigger: bool,
+}
+
+impl Job {
+    pub fn new<F, Fut>(name: impl Into<String>, mut run: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Duration> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            run: Box::new(move || Box::pin(run())),
+            fired: None,
+            wait_for_trigger: false,
+        }
+    }
+
+    /// Also runs the job whenever `trigger` fires.
+    pub fn triggered_by(mut self, trigger: &Trigger) -> Self {
+        self.fired = Some(trigger.0.subscribe());
+        self
+    }
+
+    /// Waits for the first fire of the trigger instead of running at start.
+    pub fn wait_for_trigger(mut self) -> Self {
+        self.wait_for_trigger = true;
+        self
+    }
+
+    async fn run_forever(mut self) {
+        if self.wait_for_trigger {
+            if let Some(fired) = self.fired.as_mut() {
+                let _ = fired.changed().await;
+            }
+        }
+        loop {
+            let delay = (self.run)().await;
+            tracing::debug!("job {} runs again in {delay:?}", self.name);
+            let sleep = tokio::time::sleep(delay);
+            match self.fired.as_mut() {
+                Some(rx) => tokio::select! {
+                    _ = sleep => {}
+                    _ = rx.changed() => {
+                        tracing::debug!("job {} triggered", self.name);
+                    }
+                },
+                None => sleep.await,
+            }
+        }
+    }
+}
+
+/// Runs every job concurrently, each on its own schedule.
+///
+/// Jobs share state by capturing it, e.g. an `Arc<WeLoveClient>`; state that several jobs
+/// read and write should sit behind a `tokio::sync::Mutex` held for the whole update.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn job(mut self, job: Job) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// Starts the jobs; they stop when the returned set is dropped.
+    pub fn spawn(self) -> JoinSet<()> {
+        let mut set = JoinSet::new();
+        for job in self.jobs {
+            set.spawn(job.run_forever());
+        }
+        set
+    }
+
+    /// Runs the jobs until one of them panics.
+    pub async fn run(self) {
+        let mut set = self.spawn();
+        if let Some(Err(err)) = set.join_next().await {
+            tracing::error!("job failed: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_job(name: &str, delay: Duration) -> (Job, Arc<AtomicUsize>) {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let job = Job::new(name, {
+            let runs = runs.clone();
+            move || {
+                runs.fetch_add(1, Ordering::SeqCst);
+                async move { delay }
+            }
+        });
+        (job, runs)
+    }
+
+    #[tokio::test]
+    async fn test_jobs_run_independently() {
+        let (fast, fast_runs) = counting_job("fast", Duration::from_millis(10));
+        let (slow, slow_runs) = counting_job("slow", Duration::from_secs(60));
+        let blocked = Job::new("blocked", || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Duration::ZERO
+        });
+        let _jobs = Scheduler::new().job(fast).job(slow).job(blocked).spawn();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(fast_runs.load(Ordering::SeqCst) >= 5);
+        assert_eq!(slow_runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trigger() {
+        let trigger = Trigger::new();
+        let (job, runs) = counting_job("triggered", Duration::from_secs(60));
+        let (waiting, waiting_runs) = counting_job("waiting", Duration::from_secs(60));
+        let _jobs = Scheduler::new()
+            .job(job.triggered_by(&trigger))
+            .job(waiting.triggered_by(&trigger).wait_for_trigger())
+            .spawn();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(waiting_runs.load(Ordering::SeqCst), 0);
+
+        trigger.fire();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(waiting_runs.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,193 @@
+use super::{SimStats, Simulator};
+use crate::api::market::MarketApi;
+use crate::api::order::OrderApi;
+use crate::api::panorama::PanoramaApi;
+use crate::api::stall::StallApi;
+use crate::bot::executor::Executor;
+use crate::bot::rewards::RewardQueue;
+use crate::bot::{Action, FarmState, Strategy};
+use crate::catalog::ItemCatalog;
+use crate::error::WeLoveResult;
+use crate::timeline::Timeline;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// What a strategy earned during a [`backtest`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    pub duration: Duration,
+    pub coin: i64,
+    pub exp: i64,
+    /// Change of the warehouse value at catalog sell prices, so that stock built up
+    /// or spent is not missed by `coin`.
+    pub stock_value: f64,
+    pub actions: usize,
+    pub failures: usize,
+    pub stats: SimStats,
+}
+
+impl BacktestReport {
+    fn hours(&self) -> f64 {
+        self.duration.as_secs_f64() / 3600.0
+    }
+
+    pub fn coin_per_hour(&self) -> f64 {
+        self.coin as f64 / self.hours()
+    }
+
+    pub fn exp_per_hour(&self) -> f64 {
+        self.exp as f64 / self.hours()
+    }
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "duration: {:.1}h", self.hours())?;
+        writeln!(
+            f,
+            "coin: {} ({:.1}/h), exp: {} ({:.1}/h), stock value: {:+.1}",
+            self.coin,
+            self.coin_per_hour(),
+            self.exp,
+            self.exp_per_hour(),
+            self.stock_value
+        )?;
+        writeln!(f, "actions: {}, failures: {}", self.actions, self.failures)?;
+        write!(f, "{:?}", self.stats)
+    }
+}
+
+fn stock_value(items: &HashMap<i64, i64>, catalog: &ItemCatalog) -> f64 {
+    items
+        .iter()
+        .map(|(item_id, count)| {
+            catalog.get(*item_id).map_or(0.0, |item| item.sell_price) * *count as f64
+        })
+        .sum()
+}
+
+/// Runs `strategy` against `sim` for `duration` of simulated time, the way the bot
+/// runs it: claim due rewards, query everything, plan, execute, then sleep until the
+/// next event. Rewards are claimed `reward_delay` after their order is accomplished.
+pub async fn backtest(
+    strategy: &mut dyn Strategy,
+    sim: &Simulator,
+    catalog: &ItemCatalog,
+    timeline: &Timeline,
+    reward_delay: Duration,
+    duration: Duration,
+) -> WeLoveResult<BacktestReport> {
+    let (start_coin, start_exp) = (sim.coin(), sim.exp());
+    let start_value = stock_value(&sim.warehouse(), catalog);
+    let end = sim.now() + duration.as_secs() as i64;
+    let executor = Executor::new(sim, catalog);
+    let mut actions = 0;
+    let mut failures = 0;
+    let mut rewards = RewardQueue::new();
+    while sim.now() < end {
+        for order_id in rewards.due(sim.now()) {
+            actions += 1;
+            match sim.order_reward(order_id).await {
+                Ok(_) => rewards.claimed(order_id),
+                Err(err) => {
+                    failures += 1;
+                    rewards.failed(order_id, &err, sim.now());
+                }
+            }
+        }
+
+        let mut state = FarmState::from(sim.snapshot().await?);
+        state.stall = Some(sim.stall_query().await?);
+        let mut order_info = sim.order_query().await?;
+        rewards.queue_finished(&mut order_info.orders, sim.now());
+        state.orders = Some(order_info);
+        state.market = Some(sim.market_query().await?);
+
+        let mut warehouse_items = state.warehouse_items.clone();
+        let outcomes = executor
+            .execute(strategy.plan(&state), &mut warehouse_items)
+            .await;
+        actions += outcomes.len();
+        failures += outcomes
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count();
+        for (action, result) in outcomes.iter() {
+            if let (Action::Accomplish { order_id, .. }, Ok(_)) = (action, result) {
+                rewards.push(*order_id, sim.now() + reward_delay.as_secs() as i64);
+            }
+        }
+
+        let now = Instant::now();
+        let mut timeline = timeline.clone();
+        timeline.add_fields(&sim.get_fields().await?, catalog, now);
+        timeline.add_orders(&sim.order_query().await?.orders, now);
+        timeline.add_market(&sim.market_query().await?, now);
+        timeline.add_stall(&sim.stall_query().await?, now);
+        let mut delay = timeline.next_delay(now);
+        if let Some(due) = rewards.next_due() {
+            delay = delay.min(Duration::from_secs((due - sim.now()).max(0) as u64));
+        }
+        // Always move on, or a zero `min_sleep` with a due event would loop forever.
+        let delay = delay.max(Duration::from_secs(1));
+        sim.advance(delay.min(Duration::from_secs((end - sim.now()) as u64)));
+    }
+    Ok(BacktestReport {
+        duration,
+        coin: sim.coin() - start_coin,
+        exp: sim.exp() - start_exp,
+        stock_value: stock_value(&sim.warehouse(), catalog) - start_value,
+        actions,
+        failures,
+        stats: sim.stats(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::wheat::WheatStrategy;
+    use crate::catalog::tests::test_catalog;
+    use crate::sim::SimConfig;
+
+    #[tokio::test]
+    async fn test_backtest() {
+        let catalog = test_catalog();
+        let run = || async {
+            let sim = Simulator::new(SimConfig::default(), catalog.clone());
+            backtest(
+                &mut WheatStrategy::default(),
+                &sim,
+                &catalog,
+                &Timeline::new(),
+                Duration::from_secs(10),
+                Duration::from_secs(6 * 3600),
+            )
+            .await
+            .unwrap()
+        };
+        let report = run().await;
+        assert_eq!(report, run().await);
+        assert!(report.coin > 0, "{report}");
+        assert!(report.exp > 0, "{report}");
+        assert!(report.stats.harvested > 0);
+        assert!(report.stats.stall_sold > 0);
+        assert_eq!(report.failures, 0, "{report}");
+
+        let mut timeline = Timeline::new();
+        timeline.min_sleep = Duration::ZERO;
+        let sim = Simulator::new(SimConfig::default(), catalog.clone());
+        backtest(
+            &mut WheatStrategy::default(),
+            &sim,
+            &catalog,
+            &timeline,
+            Duration::from_secs(10),
+            Duration::from_secs(600),
+        )
+        .await
+        .unwrap();
+    }
+}
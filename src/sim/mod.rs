@@ -0,0 +1,763 @@
+//! A deterministic in-process farm for backtesting strategies.
+//!
+//! [`Simulator`] implements the same api traits as [`WeLoveClient`](crate::api::WeLoveClient)
+//! and answers with the same messages, but runs on a simulated clock that only moves
+//! through [`Simulator::advance`]. All randomness comes from a seeded RNG, so a run with
+//! the same seed, config and strategy always ends the same way.
+//!
+//! The farm rules live in [`SimFarm`], which also backs the api mock server.
+pub mod backtest;
+
+use crate::api::crops::{CropsApi, Farmland};
+use crate::api::market::{MarketApi, MarketInfo, MarketItem};
+use crate::api::message::Message;
+use crate::api::order::{Order, OrderApi, OrderInfo, OrderItem};
+use crate::api::panorama::{Field, ItemInfo, PanoramaApi, Warehouse};
+use crate::api::stall::{StallApi, StallInfo, StallItem};
+use crate::api::{FromResponse, Response};
+use crate::catalog::ItemCatalog;
+use crate::error::{WeLoveError, WeLoveResult};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Knobs of the simulated economy.
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub seed: u64,
+    /// Unix time the simulation starts at.
+    pub start_time: i64,
+    pub fields: usize,
+    pub coin: i64,
+    /// Items in the warehouse at start.
+    pub warehouse: HashMap<i64, i64>,
+    /// Units harvested per field.
+    pub harvest_yield: i64,
+    pub stall_capacity: i64,
+    /// Longest time a listing at the highest stall price waits for a buyer;
+    /// cheaper listings sell proportionally sooner.
+    pub max_sale_delay: Duration,
+    pub order_slots: i64,
+    /// How long a slot stays empty after its order is refused.
+    pub order_cooldown: Duration,
+    pub market_refresh: Duration,
+    /// Market price per unit, as a multiple of the catalog sell price.
+    pub market_markup: f64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 520,
+            start_time: 1_700_000_000,
+            fields: 12,
+            coin: 1000,
+            warehouse: HashMap::from([(201001, 20)]),
+            harvest_yield: 2,
+            stall_capacity: 4,
+            max_sale_delay: Duration::from_secs(3600),
+            order_slots: 6,
+            order_cooldown: Duration::from_secs(600),
+            market_refresh: Duration::from_secs(3600),
+            market_markup: 3.0,
+        }
+    }
+}
+
+/// Counts of what happened during a simulation.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SimStats {
+    pub harvested: i64,
+    pub stall_sold: i64,
+    pub orders_accomplished: i64,
+    pub orders_refused: i64,
+    pub market_bought: i64,
+}
+
+/// Farm state and the rules of each route, shared by [`Simulator`] and the api mock
+/// server. Routes answer with the response messages, or a result code and message.
+pub struct SimFarm {
+    config: SimConfig,
+    catalog: ItemCatalog,
+    rng: StdRng,
+    /// Current unix time. Only [`tick`](Self::tick) sells listings and fills order slots.
+    pub now: i64,
+    pub coin: i64,
+    pub exp: i64,
+    pub fields: Vec<Field>,
+    pub warehouse: HashMap<i64, i64>,
+    pub stall: StallInfo,
+    /// When each listing on the stall is bought, by stall sale id.
+    sale_at: HashMap<i64, i64>,
+    pub orders: Vec<Order>,
+    /// When each empty order slot gets a new order.
+    pub slot_ready_at: HashMap<i64, i64>,
+    pub market: MarketInfo,
+    stats: SimStats,
+    next_id: i64,
+}
+
+/// The simulated farm. See the [module docs](self).
+pub struct Simulator {
+    farm: Mutex<SimFarm>,
+}
+
+impl Simulator {
+    pub fn new(config: SimConfig, catalog: ItemCatalog) -> Self {
+        Self {
+            farm: Mutex::new(SimFarm::new(config, catalog)),
+        }
+    }
+
+    /// Current simulated unix time.
+    pub fn now(&self) -> i64 {
+        self.farm.lock().unwrap().now
+    }
+
+    /// Moves the clock forward: sells stall listings, fills order slots and refreshes
+    /// the market whose time has come.
+    pub fn advance(&self, duration: Duration) {
+        let mut farm = self.farm.lock().unwrap();
+        farm.now += duration.as_secs() as i64;
+        farm.tick();
+    }
+
+    pub fn coin(&self) -> i64 {
+        self.farm.lock().unwrap().coin
+    }
+
+    pub fn exp(&self) -> i64 {
+        self.farm.lock().unwrap().exp
+    }
+
+    pub fn warehouse(&self) -> HashMap<i64, i64> {
+        self.farm.lock().unwrap().warehouse.clone()
+    }
+
+    pub fn stats(&self) -> SimStats {
+        self.farm.lock().unwrap().stats.clone()
+    }
+
+    fn call<T: FromResponse>(&self, f: impl FnOnce(&mut SimFarm) -> SimResult) -> WeLoveResult<T> {
+        let mut farm = self.farm.lock().unwrap();
+        match f(&mut farm) {
+            Ok(messages) => T::from_response(Response {
+                result: 0,
+                messages,
+                error_msg: String::new(),
+            }),
+            Err((result, msg)) => Err(WeLoveError::Server { result, msg }),
+        }
+    }
+}
+
+pub type SimResult = Result<Vec<Value>, (u32, String)>;
+
+fn message<T: serde::Serialize>(msg_type: i64, body: &T) -> Value {
+    let mut value = serde_json::to_value(body).unwrap();
+    value["msg_type"] = msg_type.into();
+    value
+}
+
+/// Result code of every request the simulator rejects.
+const RESULT_INVALID: u32 = 1;
+
+fn invalid(msg: String) -> (u32, String) {
+    (RESULT_INVALID, msg)
+}
+
+impl SimFarm {
+    pub fn new(config: SimConfig, catalog: ItemCatalog) -> Self {
+        let now = config.start_time;
+        let fields = (0..config.fields as i64)
+            .map(|i| Field {
+                id: i + 1,
+                x: 10 + i / 4,
+                y: 10 + i % 4,
+                plant_item_id: -1,
+                ..Default::default()
+            })
+            .collect();
+        let mut farm = SimFarm {
+            rng: StdRng::seed_from_u64(config.seed),
+            now,
+            coin: config.coin,
+            exp: 0,
+            fields,
+            warehouse: config.warehouse.clone(),
+            stall: StallInfo {
+                msg_type: Message::STALL,
+                capacity: config.stall_capacity,
+                farm_id: "1".to_string(),
+                ..Default::default()
+            },
+            sale_at: HashMap::new(),
+            orders: Vec::new(),
+            slot_ready_at: HashMap::new(),
+            market: MarketInfo {
+                msg_type: Message::MARKET,
+                ..Default::default()
+            },
+            stats: SimStats::default(),
+            next_id: 1,
+            config,
+            catalog,
+        };
+        for slot in 1..=farm.config.order_slots {
+            let order = farm.new_order(slot);
+            farm.orders.push(order);
+        }
+        farm.refresh_market();
+        farm
+    }
+
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Sells stall listings, fills order slots and refreshes the market whose time has come.
+    pub fn tick(&mut self) {
+        let now = self.now;
+        for item in self.stall.stall_items.iter_mut() {
+            if item.status == 1 && self.sale_at.get(&item.id).is_some_and(|at| *at <= now) {
+                item.status = 2;
+                item.buyer_farm_name = "sim buyer".to_string();
+                self.stats.stall_sold += 1;
+            }
+        }
+        let mut ready_slots: Vec<_> = self
+            .slot_ready_at
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(slot, _)| *slot)
+            .collect();
+        ready_slots.sort();
+        for slot in ready_slots {
+            self.slot_ready_at.remove(&slot);
+            self.orders.retain(|o| !(o.slot == slot && o.status == 0));
+            let order = self.new_order(slot);
+            self.orders.push(order);
+        }
+        if self.market.next_refresh_time <= now {
+            self.refresh_market();
+        }
+    }
+
+    fn new_order(&mut self, slot: i64) -> Order {
+        let mut crops: Vec<_> = self
+            .catalog
+            .crops()
+            .map(|item| (item.item_id, item.sell_price))
+            .collect();
+        crops.sort_by_key(|(item_id, _)| *item_id);
+        let kinds = self.rng.gen_range(1..=2);
+        let picked: Vec<_> = crops
+            .choose_multiple(&mut self.rng, kinds)
+            .copied()
+            .collect();
+        let mut items = Vec::new();
+        let mut value = 0.0;
+        for (item_id, sell_price) in picked {
+            let count = self.rng.gen_range(1..=3);
+            value += sell_price * count as f64;
+            items.push(OrderItem { item_id, count });
+        }
+        let total: i64 = items.iter().map(|item| item.count).sum();
+        let voucher_item_id = if self.rng.gen_bool(0.1) { 310001 } else { 0 };
+        Order {
+            order_id: self.next_id(),
+            slot,
+            items,
+            time_left: -1,
+            coin: (value * 3.0).ceil() as i64,
+            exp: total * 2,
+            voucher_item_id,
+            ..Default::default()
+        }
+    }
+
+    fn refresh_market(&mut self) {
+        let mut materials: Vec<_> = self
+            .catalog
+            .items()
+            .filter(|item| !item.is_crop() && item.sell_price > 0.0)
+            .map(|item| (item.item_id, item.sell_price))
+            .collect();
+        materials.sort_by_key(|(item_id, _)| *item_id);
+        let picked: Vec<_> = materials
+            .choose_multiple(&mut self.rng, 4)
+            .copied()
+            .collect();
+        let mut items = Vec::new();
+        for (item_id, sell_price) in picked {
+            let count = self.rng.gen_range(1..=3);
+            items.push(MarketItem {
+                id: self.next_id(),
+                item_id,
+                count,
+                sold_out: 0,
+                coin: (sell_price * count as f64 * self.config.market_markup).ceil() as i64,
+            });
+        }
+        self.market.market_item_list = items;
+        self.market.next_refresh_time = self.now + self.config.market_refresh.as_secs() as i64;
+    }
+
+    fn growth_secs(&self, item_id: i64) -> i64 {
+        self.catalog
+            .growth_time(item_id)
+            .map_or(0, |d| d.as_secs() as i64)
+    }
+
+    pub fn panorama(&self) -> Vec<Value> {
+        vec![self.fields_message(), self.warehouses_message()]
+    }
+
+    pub fn fields_message(&self) -> Value {
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .cloned()
+            .map(|mut f| {
+                f.left_time = if f.plant_item_id == -1 {
+                    0
+                } else {
+                    match f.plant_time + self.growth_secs(f.plant_item_id) - self.now {
+                        left if left > 0 => left,
+                        _ => -1,
+                    }
+                };
+                f
+            })
+            .collect();
+        json!({"msg_type": Message::FIELDS, "fields": fields})
+    }
+
+    pub fn warehouses_message(&self) -> Value {
+        let mut warehouses: Vec<Warehouse> = Vec::new();
+        let mut items: Vec<_> = self.warehouse.iter().collect();
+        items.sort();
+        for (&item_id, &count) in items {
+            let category = self.catalog.get(item_id).map_or(0, |item| item.category);
+            let item = ItemInfo { count, item_id };
+            match warehouses.iter_mut().find(|w| w.category == category) {
+                Some(w) => w.items.push(item),
+                None => warehouses.push(Warehouse {
+                    category,
+                    items: vec![item],
+                }),
+            }
+        }
+        json!({"msg_type": Message::WAREHOUSES, "warehouses": warehouses})
+    }
+
+    pub fn orders_message(&self) -> Value {
+        let mut orders = self.orders.clone();
+        for o in orders.iter_mut() {
+            if let Some(at) = self.slot_ready_at.get(&o.slot).filter(|_| o.status == 0) {
+                o.time_left = at - self.now;
+            }
+        }
+        message(
+            Message::ORDERS,
+            &OrderInfo {
+                op_time: self.now,
+                msg_type: Message::ORDERS,
+                orders,
+            },
+        )
+    }
+
+    pub fn stall_message(&self) -> Value {
+        let mut stall = self.stall.clone();
+        stall.op_time = self.now;
+        message(Message::STALL, &stall)
+    }
+
+    pub fn market_message(&self) -> Value {
+        let mut market = self.market.clone();
+        market.op_time = self.now;
+        message(Message::MARKET, &market)
+    }
+
+    fn take_items(&mut self, item_id: i64, count: i64) -> Result<(), (u32, String)> {
+        let stock = self.warehouse.entry(item_id).or_default();
+        if *stock < count {
+            return Err(invalid(format!("item {item_id} is not enough")));
+        }
+        *stock -= count;
+        Ok(())
+    }
+
+    pub fn crops_plant(&mut self, item_id: i64, farmlands: Vec<Farmland>) -> SimResult {
+        if self.growth_secs(item_id) == 0 {
+            return Err(invalid(format!("{item_id} is not a crop")));
+        }
+        for farmland in farmlands.iter() {
+            let field = self
+                .fields
+                .iter()
+                .find(|f| f.id == farmland.id)
+                .ok_or_else(|| invalid(format!("farmland {} not found", farmland.id)))?;
+            if field.plant_item_id != -1 {
+                return Err(invalid(format!("farmland {} is busy", field.id)));
+            }
+        }
+        let now = self.now;
+        for farmland in farmlands {
+            if let Some(field) = self.fields.iter_mut().find(|f| f.id == farmland.id) {
+                field.plant_item_id = item_id;
+                field.plant_time = now;
+            }
+        }
+        Ok(vec![self.fields_message()])
+    }
+
+    pub fn crops_harvest(&mut self, item_id: i64, farmland_ids: Vec<i64>) -> SimResult {
+        let growth = self.growth_secs(item_id);
+        for id in farmland_ids.iter() {
+            let field = self
+                .fields
+                .iter()
+                .find(|f| f.id == *id && f.plant_item_id == item_id)
+                .ok_or_else(|| invalid(format!("farmland {id} has no {item_id}")))?;
+            if field.plant_time + growth > self.now {
+                return Err(invalid(format!("farmland {id} is not ready")));
+            }
+        }
+        for id in farmland_ids.iter() {
+            if let Some(field) = self.fields.iter_mut().find(|f| f.id == *id) {
+                field.plant_item_id = -1;
+                field.plant_time = 0;
+            }
+        }
+        let count = self.config.harvest_yield * farmland_ids.len() as i64;
+        *self.warehouse.entry(item_id).or_default() += count;
+        self.stats.harvested += count;
+        self.exp += farmland_ids.len() as i64;
+        Ok(vec![self.fields_message(), self.warehouses_message()])
+    }
+
+    pub fn market_sale(&mut self, item_id: i64, count: i64) -> SimResult {
+        let price = self
+            .catalog
+            .get(item_id)
+            .ok_or_else(|| invalid(format!("item {item_id} not found")))?
+            .sell_price;
+        self.take_items(item_id, count)?;
+        self.coin += (price * count as f64).floor() as i64;
+        Ok(vec![self.warehouses_message()])
+    }
+
+    pub fn market_buy(&mut self, id: i64) -> SimResult {
+        let item = self
+            .market
+            .market_item_list
+            .iter()
+            .find(|item| item.id == id && item.sold_out == 0)
+            .ok_or_else(|| invalid(format!("market item {id} not found")))?
+            .clone();
+        if item.coin > self.coin {
+            return Err(invalid("coin is not enough".to_string()));
+        }
+        if let Some(item) = self
+            .market
+            .market_item_list
+            .iter_mut()
+            .find(|item| item.id == id)
+        {
+            item.sold_out = 1;
+        }
+        self.coin -= item.coin;
+        *self.warehouse.entry(item.item_id).or_default() += item.count;
+        self.stats.market_bought += 1;
+        Ok(vec![self.market_message(), self.warehouses_message()])
+    }
+
+    fn order_position(&self, order_id: i64, status: i64) -> Result<usize, (u32, String)> {
+        self.orders
+            .iter()
+            .position(|o| {
+                o.order_id == order_id
+                    && o.status == status
+                    && !(status == 0 && self.slot_ready_at.contains_key(&o.slot))
+            })
+            .ok_or_else(|| invalid(format!("order {order_id} not found")))
+    }
+
+    pub fn order_refuse(&mut self, order_id: i64) -> SimResult {
+        let index = self.order_position(order_id, 0)?;
+        let slot = self.orders[index].slot;
+        self.orders[index] = Order {
+            slot,
+            ..Default::default()
+        };
+        self.slot_ready_at
+            .insert(slot, self.now + self.config.order_cooldown.as_secs() as i64);
+        self.stats.orders_refused += 1;
+        Ok(vec![self.orders_message()])
+    }
+
+    pub fn order_accomplish(&mut self, order_id: i64) -> SimResult {
+        let index = self.order_position(order_id, 0)?;
+        let items = self.orders[index].items.clone();
+        if let Some(item) = items.iter().find(|item| {
+            self.warehouse
+                .get(&item.item_id)
+                .copied()
+                .unwrap_or_default()
+                < item.count
+        }) {
+            return Err(invalid(format!("item {} is not enough", item.item_id)));
+        }
+        for item in items.iter() {
+            self.take_items(item.item_id, item.count)?;
+        }
+        self.orders[index].status = 1;
+        let slot = self.orders[index].slot;
+        let new_order = self.new_order(slot);
+        self.orders.push(new_order.clone());
+        self.stats.orders_accomplished += 1;
+        Ok(vec![
+            self.warehouses_message(),
+            message(Message::NEW_ORDER, &new_order),
+        ])
+    }
+
+    pub fn order_reward(&mut self, order_id: i64) -> SimResult {
+        let index = self.order_position(order_id, 1)?;
+        let order = self.orders.remove(index);
+        self.coin += order.coin;
+        self.exp += order.exp;
+        Ok(vec![self.orders_message()])
+    }
+
+    pub fn stall_earn(&mut self, slot: i64, stall_sale_id: i64) -> SimResult {
+        let index = self
+            .stall
+            .stall_items
+            .iter()
+            .position(|item| item.slot == slot && item.id == stall_sale_id && item.status == 2)
+            .ok_or_else(|| invalid(format!("stall item {stall_sale_id} not sold")))?;
+        let item = self.stall.stall_items.remove(index);
+        self.sale_at.remove(&item.id);
+        self.coin += item.coin;
+        Ok(vec![self.stall_message()])
+    }
+
+    pub fn stall_onshelf(
+        &mut self,
+        slot: i64,
+        item_id: i64,
+        count: i64,
+        coin: i64,
+        ad: bool,
+    ) -> SimResult {
+        if slot < 1 || slot > self.stall.capacity {
+            return Err(invalid(format!("slot {slot} not found")));
+        }
+        if self
+            .stall
+            .stall_items
+            .iter()
+            .any(|item| item.slot == slot && item.status != 2)
+        {
+            return Err(invalid(format!("slot {slot} is busy")));
+        }
+        let max_coin = self
+            .catalog
+            .get(item_id)
+            .ok_or_else(|| invalid(format!("item {item_id} not found")))?
+            .max_stall_coin(count);
+        if coin <= 0 || coin > max_coin {
+            return Err(invalid(format!("coin {coin} is not in 1..={max_coin}")));
+        }
+        self.take_items(item_id, count)?;
+        // A sold listing is earned when its slot is reused.
+        if let Some(index) = self
+            .stall
+            .stall_items
+            .iter()
+            .position(|item| item.slot == slot)
+        {
+            let item = self.stall.stall_items.remove(index);
+            self.sale_at.remove(&item.id);
+            self.coin += item.coin;
+        }
+        if ad {
+            self.stall.last_free_ad_time = self.now;
+        }
+        let id = self.next_id();
+        let max_delay = self.config.max_sale_delay.as_secs_f64() * coin as f64 / max_coin as f64;
+        let delay = self.rng.gen_range(0.0..=max_delay) as i64;
+        self.sale_at.insert(id, self.now + delay.max(1));
+        self.stall.stall_items.push(StallItem {
+            id,
+            slot,
+            item_id,
+            count,
+            coin,
+            status: 1,
+            last_ad_time: if ad { self.now } else { 0 },
+            ..Default::default()
+        });
+        Ok(vec![self.stall_message(), self.warehouses_message()])
+    }
+}
+
+impl PanoramaApi for Simulator {
+    async fn panorama(&self) -> WeLoveResult<Response> {
+        self.call(|farm| Ok(farm.panorama()))
+    }
+
+    async fn get_fields(&self) -> WeLoveResult<Vec<Field>> {
+        self.call(|farm| Ok(vec![farm.fields_message()]))
+    }
+
+    async fn get_warehouses(&self) -> WeLoveResult<Vec<Warehouse>> {
+        self.call(|farm| Ok(vec![farm.warehouses_message()]))
+    }
+}
+
+impl CropsApi for Simulator {
+    async fn crops_plant(&self, item_id: i64, farmlands: Vec<Farmland>) -> WeLoveResult<Response> {
+        self.call(|farm| farm.crops_plant(item_id, farmlands))
+    }
+
+    async fn crops_harvest(&self, item_id: i64, farmland_ids: Vec<i64>) -> WeLoveResult<Response> {
+        self.call(|farm| farm.crops_harvest(item_id, farmland_ids))
+    }
+}
+
+impl MarketApi for Simulator {
+    async fn market_sale(&self, item_id: i64, count: i64) -> WeLoveResult<Response> {
+        self.call(|farm| farm.market_sale(item_id, count))
+    }
+
+    async fn market_query(&self) -> WeLoveResult<MarketInfo> {
+        self.call(|farm| Ok(vec![farm.market_message()]))
+    }
+
+    async fn market_buy(&self, id: i64) -> WeLoveResult<Response> {
+        self.call(|farm| farm.market_buy(id))
+    }
+}
+
+impl OrderApi for Simulator {
+    async fn order_query(&self) -> WeLoveResult<OrderInfo> {
+        self.call(|farm| Ok(vec![farm.orders_message()]))
+    }
+
+    async fn order_refuse(&self, order_id: i64) -> WeLoveResult<Response> {
+        self.call(|farm| farm.order_refuse(order_id))
+    }
+
+    async fn order_accomplish(
+        &self,
+        order_id: i64,
+        _by_rainbow_coin: bool,
+    ) -> WeLoveResult<Response> {
+        self.call(|farm| farm.order_accomplish(order_id))
+    }
+
+    async fn order_reward(&self, order_id: i64) -> WeLoveResult<Response> {
+        self.call(|farm| farm.order_reward(order_id))
+    }
+}
+
+impl StallApi for Simulator {
+    async fn stall_query(&self) -> WeLoveResult<StallInfo> {
+        self.call(|farm| Ok(vec![farm.stall_message()]))
+    }
+
+    async fn stall_earn(&self, slot: i64, stall_sale_id: i64) -> WeLoveResult<Response> {
+        self.call(|farm| farm.stall_earn(slot, stall_sale_id))
+    }
+
+    async fn stall_buy(&self, stall_sale_id: i64, _seller_farm_id: i64) -> WeLoveResult<Response> {
+        Err(WeLoveError::Other(format!(
+            "stall_buy {stall_sale_id}: other farms are not simulated"
+        )))
+    }
+
+    async fn stall_onshelf(
+        &self,
+        slot: i64,
+        item_id: i64,
+        count: i64,
+        coin: i64,
+        ad: bool,
+        _rainbow_coin: i64,
+    ) -> WeLoveResult<Response> {
+        self.call(|farm| farm.stall_onshelf(slot, item_id, count, coin, ad))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::test_catalog;
+
+    #[tokio::test]
+    async fn test_growth_and_stall() {
+        let sim = Simulator::new(SimConfig::default(), test_catalog());
+        let farmland = Farmland {
+            id: 1,
+            last_interval: 1,
+            x: 10,
+            y: 10,
+        };
+        sim.crops_plant(201001, vec![farmland]).await.unwrap();
+        assert!(sim.crops_harvest(201001, vec![1]).await.is_err());
+        sim.advance(Duration::from_secs(120));
+        sim.crops_harvest(201001, vec![1]).await.unwrap();
+        assert_eq!(sim.warehouse()[&201001], 22);
+
+        sim.stall_onshelf(1, 201001, 10, 36, true, 0).await.unwrap();
+        assert!(matches!(
+            sim.stall_onshelf(1, 201001, 10, 36, false, 0).await,
+            Err(WeLoveError::Server { .. })
+        ));
+        sim.advance(Duration::from_secs(3600));
+        let item = sim.stall_query().await.unwrap().stall_items[0].clone();
+        assert_eq!(item.status, 2);
+        sim.stall_earn(1, item.id).await.unwrap();
+        assert_eq!(sim.coin(), 1036);
+    }
+
+    #[tokio::test]
+    async fn test_orders_cooldown() {
+        let sim = Simulator::new(SimConfig::default(), test_catalog());
+        let order = sim.order_query().await.unwrap().orders[0].clone();
+        sim.order_refuse(order.order_id).await.unwrap();
+        let empty = sim.order_query().await.unwrap().orders[0].clone();
+        assert_eq!(empty.time_left, 600);
+        sim.advance(Duration::from_secs(600));
+        let orders = sim.order_query().await.unwrap().orders;
+        assert!(orders
+            .iter()
+            .any(|o| o.slot == order.slot && o.time_left < 0 && !o.items.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_deterministic() {
+        let orders = |seed| async move {
+            let sim = Simulator::new(
+                SimConfig {
+                    seed,
+                    ..Default::default()
+                },
+                test_catalog(),
+            );
+            let info = sim.order_query().await.unwrap();
+            serde_json::to_string(&info.orders).unwrap()
+        };
+        assert_eq!(orders(1).await, orders(1).await);
+        assert_ne!(orders(1).await, orders(2).await);
+    }
+}
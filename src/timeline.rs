@@ -0,0 +1,244 @@
+use crate::api::market::MarketInfo;
+use crate::api::order::Order;
+use crate::api::panorama::Field;
+use crate::api::stall::StallInfo;
+use crate::catalog::ItemCatalog;
+use crate::utils::unix_now;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Something that needs doing once its time has come.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    CropReady {
+        field_id: i64,
+        item_id: i64,
+    },
+    /// An empty order slot receives a new order.
+    OrderSlot {
+        slot: i64,
+    },
+    MarketRefresh,
+    /// Items are on sale in the stall; there is no sale time, so it is polled.
+    StallPoll,
+}
+
+/// Upcoming events, used to sleep exactly until the next one instead of a fixed interval.
+///
+/// Events are collected from the responses of one loop. Events that are already due
+/// were handled by that loop, so [`next`](Self::next) only looks at later ones.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    events: Vec<(Instant, Event)>,
+    /// Shortest sleep, so that a stale timestamp can not cause a busy loop.
+    pub min_sleep: Duration,
+    /// Longest sleep, also used when no event is known.
+    pub max_sleep: Duration,
+    /// How often a stall with items on sale is polled.
+    pub stall_poll: Duration,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            min_sleep: Duration::from_secs(5),
+            max_sleep: Duration::from_secs(600),
+            stall_poll: Duration::from_secs(300),
+        }
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, at: Instant, event: Event) {
+        self.events.push((at, event));
+    }
+
+    /// Adds growing fields. `left_time` is used when the server sent it, otherwise the
+    /// catalog growth time is counted from `plant_time`.
+    pub fn add_fields(&mut self, fields: &[Field], catalog: &ItemCatalog, now: Instant) {
+        let unix_now = unix_now();
+        for f in fields.iter().filter(|f| f.plant_item_id != -1) {
+            let left_secs = if f.left_time != 0 {
+                f.left_time
+            } else {
+                match catalog.growth_time(f.plant_item_id) {
+                    Some(growth) if f.plant_time > 0 => {
+                        f.plant_time + growth.as_secs() as i64 - unix_now
+                    }
+                    _ => continue,
+                }
+            };
+            self.push(
+                after_secs(now, left_secs),
+                Event::CropReady {
+                    field_id: f.id,
+                    item_id: f.plant_item_id,
+                },
+            );
+        }
+    }
+
+    /// Adds fields that were just planted with `item_id`.
+    pub fn add_planted(
+        &mut self,
+        field_ids: &[i64],
+        item_id: i64,
+        catalog: &ItemCatalog,
+        now: Instant,
+    ) {
+        let Some(growth) = catalog.growth_time(item_id) else {
+            return;
+        };
+        for &field_id in field_ids {
+            self.push(now + growth, Event::CropReady { field_id, item_id });
+        }
+    }
+
+    /// Adds order slots that are waiting for a new order.
+    pub fn add_orders(&mut self, orders: &[Order], now: Instant) {
+        for o in orders.iter().filter(|o| o.status == 0 && o.time_left > 0) {
+            self.push(
+                after_secs(now, o.time_left),
+                Event::OrderSlot { slot: o.slot },
+            );
+        }
+    }
+
+    pub fn add_market(&mut self, market: &MarketInfo, now: Instant) {
+        if market.next_refresh_time > 0 {
+            let op_time = if market.op_time > 0 {
+                market.op_time
+            } else {
+                unix_now()
+            };
+            self.push(
+                after_secs(now, market.next_refresh_time - op_time),
+                Event::MarketRefresh,
+            );
+        }
+    }
+
+    pub fn add_stall(&mut self, stall: &StallInfo, now: Instant) {
+        if stall.stall_items.iter().any(|item| item.status == 1) {
+            self.push(now + self.stall_poll, Event::StallPoll);
+        }
+    }
+
+    /// The earliest event after `now`.
+    pub fn next(&self, now: Instant) -> Option<(Instant, Event)> {
+        self.events
+            .iter()
+            .filter(|(at, _)| *at > now)
+            .min_by_key(|(at, _)| *at)
+            .copied()
+    }
+
+    /// When to wake up: the next event, kept between `min_sleep` and `max_sleep` from `now`.
+    pub fn next_wake(&self, now: Instant) -> Instant {
+        let at = self.next(now).map_or(now + self.max_sleep, |(at, _)| at);
+        at.clamp(now + self.min_sleep, now + self.max_sleep)
+    }
+
+    /// How long to sleep from `now`, see [`next_wake`](Self::next_wake).
+    pub fn next_delay(&self, now: Instant) -> Duration {
+        self.next_wake(now) - now
+    }
+}
+
+fn after_secs(now: Instant, secs: i64) -> Instant {
+    now + Duration::from_secs(secs.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::stall::StallItem;
+    use crate::catalog::tests::test_catalog;
+
+    fn field(id: i64, plant_item_id: i64, left_time: i64) -> Field {
+        Field {
+            id,
+            plant_item_id,
+            left_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_next_event() {
+        let catalog = test_catalog();
+        let now = Instant::now();
+        let mut timeline = Timeline::new();
+        timeline.add_fields(
+            &[
+                field(1, 201001, -5),
+                field(2, 201002, 40),
+                field(3, -1, 0),
+                Field {
+                    plant_time: unix_now() - 100,
+                    ..field(4, 201001, 0)
+                },
+            ],
+            &catalog,
+            now,
+        );
+        timeline.add_orders(
+            &[Order {
+                slot: 4,
+                time_left: 300,
+                ..Default::default()
+            }],
+            now,
+        );
+        let (at, event) = timeline.next(now).unwrap();
+        assert_eq!(
+            event,
+            Event::CropReady {
+                field_id: 4,
+                item_id: 201001
+            }
+        );
+        assert!(at - now <= Duration::from_secs(20));
+        assert_eq!(
+            timeline.next(now + Duration::from_secs(30)).unwrap().1,
+            Event::CropReady {
+                field_id: 2,
+                item_id: 201002
+            }
+        );
+        assert_eq!(
+            timeline.next(now + Duration::from_secs(60)).unwrap().1,
+            Event::OrderSlot { slot: 4 }
+        );
+    }
+
+    #[test]
+    fn test_next_wake_bounds() {
+        let now = Instant::now();
+        let mut timeline = Timeline::new();
+        assert_eq!(timeline.next_wake(now), now + timeline.max_sleep);
+
+        timeline.push(now + Duration::from_secs(1), Event::MarketRefresh);
+        assert_eq!(timeline.next_wake(now), now + timeline.min_sleep);
+
+        timeline.add_stall(
+            &StallInfo {
+                stall_items: vec![StallItem {
+                    status: 1,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(
+            timeline.next_wake(now + Duration::from_secs(2)),
+            now + timeline.stall_poll
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::error::{WeLoveError, WeLoveResult};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Flattens a request struct into form params.
+///
+/// Strings and numbers are sent as is, bools as `1`/`0`, `None` fields are skipped,
+/// and nested arrays or objects are sent as JSON strings.
+pub fn to_form<T: Serialize>(req: &T) -> WeLoveResult<HashMap<String, String>> {
+    let map = match serde_json::to_value(req)? {
+        Value::Object(map) => map,
+        Value::Null => return Ok(HashMap::new()),
+        _ => return Err(WeLoveError::Other("request must be a struct".to_string())),
+    };
+    let mut params = HashMap::with_capacity(map.len());
+    for (k, v) in map {
+        let v = match v {
+            Value::Null => continue,
+            Value::String(s) => s,
+            Value::Bool(b) => (b as u32).to_string(),
+            Value::Number(n) => n.to_string(),
+            v => serde_json::to_string(&v)?,
+        };
+        params.insert(k, v);
+    }
+    Ok(params)
+}
+
+/// Serializes a list of ids as `1,2,3`.
+pub fn comma_separated<S: Serializer>(ids: &[i64], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(
+        &ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Req {
+        id: i64,
+        name: String,
+        ad: bool,
+        skipped: Option<i64>,
+        #[serde(serialize_with = "comma_separated")]
+        ids: Vec<i64>,
+        list: Vec<HashMap<String, i64>>,
+    }
+
+    #[test]
+    fn test_to_form() {
+        let params = to_form(&Req {
+            id: 1,
+            name: "a".to_string(),
+            ad: true,
+            skipped: None,
+            ids: vec![1, 2, 3],
+            list: vec![HashMap::from([("x".to_string(), 1)])],
+        })
+        .unwrap();
+        assert_eq!(params["id"], "1");
+        assert_eq!(params["name"], "a");
+        assert_eq!(params["ad"], "1");
+        assert!(!params.contains_key("skipped"));
+        assert_eq!(params["ids"], "1,2,3");
+        assert_eq!(params["list"], r#"[{"x":1}]"#);
+        assert!(to_form(&()).unwrap().is_empty());
+        assert!(to_form(&1).is_err());
+    }
+}
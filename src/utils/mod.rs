@@ -1 +1,10 @@
+pub mod form;
 pub mod sig;
+
+/// Current unix time in seconds.
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::UNIX_EPOCH
+        .elapsed()
+        .unwrap_or_default()
+        .as_secs() as i64
+}